@@ -0,0 +1,243 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Cursor, Read, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use serde::{Serialize, Deserialize};
+use log::{debug, info, warn};
+use super::bundle::Bundle;
+use super::index::{murmur_hash64a, Index};
+use crate::versioned;
+
+pub const CACHE_MAGIC: [u8; 4] = *b"EGIC";
+/// Bump whenever the serialized layout of `Index` changes.
+pub const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// Cache layout: `CACHE_MAGIC`, format version (u32 LE), then the bincode of
+/// `crate_version` and `source`, then the bincode of the `Index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheHeader {
+    pub format_version: u32,
+    pub crate_version: String,
+    pub source: Option<SourceStamp>,
+}
+
+/// Identifies the `_.index.bin` a cache was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceStamp {
+    pub size: u64,
+    pub modified: u64, // Nanoseconds since the Unix epoch
+    pub hash: u64, // MurmurHash64A of the file contents
+}
+
+impl SourceStamp {
+    pub fn from_data(data: &[u8], modified: u64) -> Self {
+        Self { size: data.len() as u64, modified, hash: murmur_hash64a(data) }
+    }
+
+    /// Whether the file at `path` is still the one this stamp describes.
+    /// Size and mtime are checked first; the contents are only hashed when the mtime differs.
+    pub fn matches_file<P: AsRef<Path>>(&self, path: P) -> io::Result<bool> {
+        let meta = fs::metadata(&path)?;
+        if meta.len() != self.size {
+            return Ok(false);
+        }
+        if modified_nanos(&meta) == self.modified {
+            return Ok(true);
+        }
+        Ok(murmur_hash64a(&fs::read(path)?) == self.hash)
+    }
+}
+
+impl CacheHeader {
+    fn new(source: Option<SourceStamp>) -> Self {
+        Self {
+            format_version: CACHE_FORMAT_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            source,
+        }
+    }
+
+    fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        versioned::write_header(&mut writer, CACHE_MAGIC, self.format_version)?;
+        versioned::serialize_into(&mut writer, &(&self.crate_version, &self.source))
+    }
+
+    /// Reads the header, failing before any bincode decoding if the magic or format version is wrong.
+    /// `limit` bounds the decoded size, see `versioned::deserialize_from`.
+    fn read<R: Read>(mut reader: R, limit: u64) -> io::Result<Self> {
+        versioned::read_header(&mut reader, CACHE_MAGIC, CACHE_FORMAT_VERSION, "an index cache file")?;
+        let (crate_version, source) = versioned::deserialize_from(&mut reader, limit)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Unreadable index cache header: {}", e)))?;
+        Ok(Self { format_version: CACHE_FORMAT_VERSION, crate_version, source })
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        if self.crate_version != env!("CARGO_PKG_VERSION") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Index cache written by exile-ggpk {}, this is {}", self.crate_version, env!("CARGO_PKG_VERSION"))));
+        }
+        Ok(())
+    }
+}
+
+impl Index {
+    /// Reads and decompresses a `_.index.bin` file.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        Self::from_bundle_data(&data)
+    }
+
+    fn from_bundle_data(data: &[u8]) -> io::Result<Self> {
+        let mut cursor = Cursor::new(data);
+        let bundle = Bundle::read_header(&mut cursor)?;
+        let index_data = bundle.decompress(&mut cursor)?;
+        Self::read(&index_data)
+    }
+
+    /// Returns the cached index if it was built from the current `index_path`, otherwise rebuilds and rewrites the cache.
+    pub fn open_cached<P: AsRef<Path>, C: AsRef<Path>>(index_path: P, cache_path: C) -> io::Result<Self> {
+        let index_path = index_path.as_ref();
+        let cache_path = cache_path.as_ref();
+
+        match Self::load_cache_file(cache_path) {
+            Ok((header, index)) => match &header.source {
+                Some(stamp) if stamp.matches_file(index_path)? => {
+                    debug!("Index::open_cached: Using cache {:?}", cache_path);
+                    return Ok(index);
+                },
+                _ => info!("Index::open_cached: Cache {:?} is stale, rebuilding", cache_path),
+            },
+            Err(e) => info!("Index::open_cached: Cache {:?} unusable ({}), rebuilding", cache_path, e),
+        }
+
+        let data = fs::read(index_path)?;
+        let stamp = SourceStamp::from_data(&data, modified_nanos(&fs::metadata(index_path)?));
+        let index = Self::from_bundle_data(&data)?;
+
+        if let Err(e) = index.write_cache_file(cache_path, Some(stamp)) {
+            warn!("Index::open_cached: Failed to write cache {:?}: {}", cache_path, e);
+        }
+        Ok(index)
+    }
+
+    pub fn save_to_cache<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_cache_file(path.as_ref(), None)
+    }
+
+    /// Loads a cache, rejecting files with the wrong magic, format version or crate version.
+    /// Does not check whether the source index has changed; use `open_cached` for that.
+    pub fn load_from_cache<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load_cache_file(path.as_ref()).map(|(_, index)| index)
+    }
+
+    /// Reads only the header of a cache file.
+    pub fn read_cache_header<P: AsRef<Path>>(path: P) -> io::Result<CacheHeader> {
+        let file = File::open(path)?;
+        let limit = file.metadata()?.len();
+        CacheHeader::read(BufReader::new(file), limit)
+    }
+
+    fn write_cache_file(&self, path: &Path, source: Option<SourceStamp>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        CacheHeader::new(source).write(&mut writer)?;
+        versioned::serialize_into(&mut writer, self)
+    }
+
+    fn load_cache_file(path: &Path) -> io::Result<(CacheHeader, Self)> {
+        let file = File::open(path)?;
+        let limit = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let header = CacheHeader::read(&mut reader, limit)?;
+        header.validate()?;
+        let index = versioned::deserialize_from(&mut reader, limit)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Corrupt index cache: {}", e)))?;
+        Ok((header, index))
+    }
+}
+
+fn modified_nanos(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundles::index::{BundleInfo, HashAlgorithm};
+    use crate::testing::TempDir;
+
+    fn empty_index(bundle: &str) -> Index {
+        let bundles = vec![BundleInfo { name: bundle.to_string(), uncompressed_size: 0 }];
+        Index { bundles, files: Default::default(), directories: Vec::new(), hash_algorithm: HashAlgorithm::Murmur64A }
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let dir = TempDir::new("cache");
+        let source = dir.path().join("_.index.bin");
+        let cache = dir.path().join("index.cache");
+        fs::write(&source, b"index contents").unwrap();
+
+        let index = empty_index("Data");
+        let stamp = SourceStamp::from_data(b"index contents", modified_nanos(&fs::metadata(&source).unwrap()));
+        index.write_cache_file(&cache, Some(stamp.clone())).unwrap();
+
+        let header = Index::read_cache_header(&cache).unwrap();
+        assert_eq!(header.source, Some(stamp.clone()));
+        assert!(Index::load_from_cache(&cache).is_ok());
+        assert!(stamp.matches_file(&source).unwrap());
+
+        fs::write(&source, b"patched index contents").unwrap();
+        assert!(!stamp.matches_file(&source).unwrap());
+
+        fs::write(&cache, b"not a cache at all").unwrap();
+        assert_eq!(Index::load_from_cache(&cache).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_open_cached() {
+        let dir = TempDir::new("open_cached");
+        let source = dir.path().join("_.index.bin");
+        let cache = dir.path().join("index.cache");
+        // Not a real bundle: any rebuild fails, so a successful open must come from the cache
+        fs::write(&source, b"index contents").unwrap();
+        let stamp = SourceStamp::from_data(b"index contents", modified_nanos(&fs::metadata(&source).unwrap()));
+        empty_index("Cached").write_cache_file(&cache, Some(stamp)).unwrap();
+
+        let index = Index::open_cached(&source, &cache).unwrap();
+        assert_eq!(index.bundles[0].name, "Cached");
+
+        // A cache without a source stamp is never trusted
+        empty_index("Unstamped").save_to_cache(&cache).unwrap();
+        assert!(Index::open_cached(&source, &cache).is_err());
+
+        // Neither is one whose source changed
+        let stamp = SourceStamp::from_data(b"index contents", modified_nanos(&fs::metadata(&source).unwrap()));
+        empty_index("Cached").write_cache_file(&cache, Some(stamp)).unwrap();
+        fs::write(&source, b"patched index contents").unwrap();
+        assert!(Index::open_cached(&source, &cache).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_length_prefix() {
+        let dir = TempDir::new("corrupt_cache");
+        let source = dir.path().join("_.index.bin");
+        let cache = dir.path().join("index.cache");
+        fs::write(&source, b"index contents").unwrap();
+
+        // Valid magic and version, then a crate version string claiming ~2^47 bytes
+        let mut data = Vec::new();
+        versioned::write_header(&mut data, CACHE_MAGIC, CACHE_FORMAT_VERSION).unwrap();
+        data.extend_from_slice(&0x7FFF_FFFF_FFFFu64.to_le_bytes());
+        data.extend_from_slice(b"garbage");
+        fs::write(&cache, &data).unwrap();
+
+        assert_eq!(Index::read_cache_header(&cache).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Index::load_from_cache(&cache).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        // The cache is skipped and the index rebuilt, which fails here only because the source is not a bundle
+        let err = Index::open_cached(&source, &cache).unwrap_err();
+        assert!(!err.to_string().contains("cache"), "{}", err);
+    }
+}
@@ -0,0 +1,168 @@
+use std::io;
+use serde::Serialize;
+use super::index::{FileInfo, Index};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiffEntry {
+    pub path_hash: u64,
+    pub path: String,
+    pub bundle: String,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MovedFile {
+    pub path_hash: u64,
+    pub path: String,
+    pub old_bundle: String,
+    pub new_bundle: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResizedFile {
+    pub path_hash: u64,
+    pub path: String,
+    pub old_size: u32,
+    pub new_size: u32,
+}
+
+/// Changes between two indexes, keyed by path hash. A file can be both moved and resized.
+/// Every list is sorted by path, then hash.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexDiff {
+    pub added: Vec<DiffEntry>,
+    pub removed: Vec<DiffEntry>,
+    pub moved: Vec<MovedFile>,
+    pub resized: Vec<ResizedFile>,
+    pub content_changed: Vec<DiffEntry>, // Only filled by `compare_contents`
+}
+
+impl IndexDiff {
+    pub fn between(old: &Index, new: &Index) -> Self {
+        let mut diff = Self::default();
+
+        for (hash, new_file) in &new.files {
+            let Some(old_file) = old.files.get(hash) else {
+                diff.added.push(entry(new, new_file));
+                continue;
+            };
+
+            let path = display_path(old_file, new_file);
+            let old_bundle = bundle_name(old, old_file);
+            let new_bundle = bundle_name(new, new_file);
+            if old_bundle != new_bundle {
+                diff.moved.push(MovedFile { path_hash: *hash, path: path.clone(), old_bundle, new_bundle });
+            }
+            if old_file.file_size != new_file.file_size {
+                diff.resized.push(ResizedFile { path_hash: *hash, path, old_size: old_file.file_size, new_size: new_file.file_size });
+            }
+        }
+
+        for (hash, old_file) in &old.files {
+            if !new.files.contains_key(hash) {
+                diff.removed.push(entry(old, old_file));
+            }
+        }
+
+        diff.added.sort_by(|a, b| (&a.path, a.path_hash).cmp(&(&b.path, b.path_hash)));
+        diff.removed.sort_by(|a, b| (&a.path, a.path_hash).cmp(&(&b.path, b.path_hash)));
+        diff.moved.sort_by(|a, b| (&a.path, a.path_hash).cmp(&(&b.path, b.path_hash)));
+        diff.resized.sort_by(|a, b| (&a.path, a.path_hash).cmp(&(&b.path, b.path_hash)));
+        diff
+    }
+
+    /// Compares the contents of files present in both indexes with unchanged size, recording those that differ
+    /// in `content_changed`. Resized files are already known to differ and are skipped.
+    /// This reads every common file, so it is as expensive as extracting both patches.
+    pub fn compare_contents<F, G>(&mut self, old: &Index, new: &Index, mut read_old: F, mut read_new: G) -> io::Result<()>
+    where
+        F: FnMut(&FileInfo) -> io::Result<Vec<u8>>,
+        G: FnMut(&FileInfo) -> io::Result<Vec<u8>>,
+    {
+        self.content_changed.clear();
+        for (hash, new_file) in &new.files {
+            let Some(old_file) = old.files.get(hash) else { continue };
+            if old_file.file_size != new_file.file_size { continue; }
+
+            if read_old(old_file)? != read_new(new_file)? {
+                let mut changed = entry(new, new_file);
+                changed.path = display_path(old_file, new_file);
+                self.content_changed.push(changed);
+            }
+        }
+        self.content_changed.sort_by(|a, b| (&a.path, a.path_hash).cmp(&(&b.path, b.path_hash)));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.resized.is_empty()
+            && self.content_changed.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+fn entry(index: &Index, file: &FileInfo) -> DiffEntry {
+    DiffEntry {
+        path_hash: file.path_hash,
+        path: file.path.clone(),
+        bundle: bundle_name(index, file),
+        size: file.file_size,
+    }
+}
+
+fn bundle_name(index: &Index, file: &FileInfo) -> String {
+    index.bundles
+        .get(file.bundle_index as usize)
+        .map(|b| b.name.clone())
+        .unwrap_or_default()
+}
+
+// The newer index may have resolved a path the older one could not, or vice versa
+fn display_path(old: &FileInfo, new: &FileInfo) -> String {
+    if new.path.is_empty() { old.path.clone() } else { new.path.clone() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundles::index::{BundleInfo, HashAlgorithm};
+
+    fn index(files: &[(u64, &str, u32, u32)]) -> Index {
+        Index {
+            bundles: ["a", "b"].iter().map(|n| BundleInfo { name: n.to_string(), uncompressed_size: 0 }).collect(),
+            files: files.iter().map(|&(hash, path, bundle_index, file_size)| (hash, FileInfo {
+                path_hash: hash,
+                bundle_index,
+                file_offset: 0,
+                file_size,
+                path: path.to_string(),
+            })).collect(),
+            directories: Vec::new(),
+            hash_algorithm: HashAlgorithm::Murmur64A,
+        }
+    }
+
+    #[test]
+    fn test_index_diff() {
+        let old = index(&[(1, "Data/Mods.datc64", 0, 10), (2, "Data/Stats.datc64", 0, 20), (3, "Data/Old.datc64", 0, 5)]);
+        let new = index(&[(1, "Data/Mods.datc64", 1, 12), (2, "Data/Stats.datc64", 0, 20), (4, "Data/New.datc64", 1, 7)]);
+
+        let mut diff = old.diff(&new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].path, "Data/New.datc64");
+        assert_eq!(diff.removed[0].path, "Data/Old.datc64");
+        assert_eq!(diff.moved[0].new_bundle, "b");
+        assert_eq!(diff.resized[0].new_size, 12);
+
+        diff.compare_contents(&old, &new, |_| Ok(vec![0]), |f| Ok(vec![f.path_hash as u8])).unwrap();
+        assert_eq!(diff.content_changed.len(), 1);
+        assert_eq!(diff.content_changed[0].path, "Data/Stats.datc64");
+        assert!(diff.to_json().unwrap().contains("Data/New.datc64"));
+    }
+}
@@ -25,22 +25,64 @@ pub struct DirectoryInfo {
     pub offset: u32,
     pub size: u32,
     pub recursive_size: u32,
+    pub path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Index {
     pub bundles: Vec<BundleInfo>,
     pub files: HashMap<u64, FileInfo>,
+    pub directories: Vec<DirectoryInfo>,
+    pub hash_algorithm: HashAlgorithm,
 }
 
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum HashAlgorithm {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
     Murmur64A,
     Fnv1a,
     Unknown,
 }
 
+impl HashAlgorithm {
+    /// Returns the hash under which `files` stores `path`, trying the casings this algorithm allows.
+    /// The path is lowercased only when its original casing is not found.
+    fn find_hash(self, files: &HashMap<u64, FileInfo>, path: &[u8]) -> Option<u64> {
+        let hashes: &[fn(&[u8]) -> u64] = match self {
+            // LibGGPK3 lowercases names before hashing with MurmurHash64A
+            HashAlgorithm::Murmur64A => &[murmur_hash64a],
+            // FNV1a indexes (pre 3.21.2) hash the original casing, lowercase is a fallback
+            HashAlgorithm::Fnv1a => &[fnv1a64],
+            // Fallback to trying everything (old slow behavior)
+            HashAlgorithm::Unknown => &[murmur_hash64a, fnv1a64],
+        };
+        let has_upper = path.iter().any(u8::is_ascii_uppercase);
+        let mut lower: Option<Vec<u8>> = None;
+        for hash in hashes {
+            let h = hash(path);
+            if files.contains_key(&h) {
+                return Some(h);
+            }
+            if has_upper {
+                let h = hash(lower.get_or_insert_with(|| path.to_ascii_lowercase()));
+                if files.contains_key(&h) {
+                    return Some(h);
+                }
+            }
+        }
+        None
+    }
+
+    /// Hash of a directory path without trailing slash; the root directory is the empty path.
+    pub fn hash_directory(self, path: &str) -> u64 {
+        match self {
+            // LibBundle hashes directories as `path++`, keeping their casing
+            HashAlgorithm::Fnv1a => fnv1a64(format!("{}++", path).as_bytes()),
+            HashAlgorithm::Murmur64A | HashAlgorithm::Unknown => murmur_hash64a(path.to_ascii_lowercase().as_bytes()),
+        }
+    }
+}
+
 impl Index {
     pub fn read(data: &[u8]) -> io::Result<Self> {
         let mut cursor = Cursor::new(data);
@@ -87,7 +129,7 @@ impl Index {
             let size = read_u32(&mut cursor)?;
             let recursive_size = read_u32(&mut cursor)?;
             
-            directories.push(DirectoryInfo { path_hash, offset, size, recursive_size });
+            directories.push(DirectoryInfo { path_hash, offset, size, recursive_size, path: String::new() });
         }
         
         let current_pos = cursor.position() as usize;
@@ -126,22 +168,88 @@ impl Index {
 
         let populated_count = files_map.values().filter(|f| !f.path.is_empty()).count();
         debug!("Index::read: {}/{} files have paths", populated_count, files_map.len());
+        if populated_count < files_map.len() {
+            warn!("Index::read: {} files have no resolved path", files_map.len() - populated_count);
+        }
         
-        Ok(Self { bundles, files: files_map })
+        Self::assign_directory_paths(&mut directories, &files_map, hash_algo);
+
+        Ok(Self { bundles, files: files_map, directories, hash_algorithm: hash_algo })
+    }
+
+    /// Builds a browsable directory tree from the resolved file paths.
+    pub fn tree(&self) -> crate::bundles::tree::BundleTree {
+        crate::bundles::tree::BundleTree::build(self)
+    }
+
+    /// Builds a sorted path index over the resolved files for prefix, extension and glob queries.
+    pub fn path_index(&self) -> crate::bundles::paths::PathIndex<'_> {
+        crate::bundles::paths::PathIndex::build(self)
+    }
+
+    /// Files added, removed, moved between bundles or resized going from `self` to `newer`.
+    pub fn diff(&self, newer: &Index) -> crate::bundles::diff::IndexDiff {
+        crate::bundles::diff::IndexDiff::between(self, newer)
+    }
+
+    /// Names directory records by hashing every parent directory of the resolved files.
+    pub(crate) fn assign_directory_paths(directories: &mut [DirectoryInfo], files: &HashMap<u64, FileInfo>, hash_algo: HashAlgorithm) {
+        let by_hash: HashMap<u64, usize> = directories.iter().enumerate().map(|(i, d)| (d.path_hash, i)).collect();
+
+        for f in files.values() {
+            let mut dir = f.path.as_str();
+            while let Some(pos) = dir.rfind('/') {
+                dir = &dir[..pos];
+                if let Some(&i) = by_hash.get(&hash_algo.hash_directory(dir)) {
+                    // Parents of an already named directory were named along with it
+                    if !directories[i].path.is_empty() { break; }
+                    directories[i].path = dir.to_string();
+                }
+            }
+        }
     }
 
-    pub fn save_to_cache<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
-        let file = std::fs::File::create(path)?;
-        let mut writer = std::io::BufWriter::new(file);
-        bincode::serialize_into(&mut writer, self)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    /// Files whose hash did not match any path reconstructed from the directory bundle, sorted by hash.
+    pub fn unresolved_files(&self) -> Vec<&FileInfo> {
+        let mut unresolved: Vec<&FileInfo> = self.files.values().filter(|f| f.path.is_empty()).collect();
+        unresolved.sort_by_key(|f| f.path_hash);
+        unresolved
     }
 
-    pub fn load_from_cache<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
-        let file = std::fs::File::open(path)?;
-        let mut reader = std::io::BufReader::new(file);
-        bincode::deserialize_from(&mut reader)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+    /// Hashes each candidate path with the index's algorithm and names the unresolved files it matches.
+    /// Returns the number of files that were newly resolved.
+    pub fn resolve_paths<I, S>(&mut self, candidates: I) -> usize
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut resolved = 0;
+        for candidate in candidates {
+            let path = candidate.as_ref();
+            if path.is_empty() { continue; }
+
+            if let Some(hash) = self.hash_algorithm.find_hash(&self.files, path.as_bytes()) {
+                if let Some(f) = self.files.get_mut(&hash) {
+                    if f.path.is_empty() {
+                        f.path = path.to_string();
+                        resolved += 1;
+                    }
+                }
+            }
+        }
+        let remaining = self.files.values().filter(|f| f.path.is_empty()).count();
+        info!("Index::resolve_paths: resolved {} files, {} still unresolved", resolved, remaining);
+        resolved
+    }
+
+    /// Loads a dictionary of candidate paths (one per line, `#` starts a comment) and resolves against it.
+    pub fn resolve_paths_from_file<P: AsRef<std::path::Path>>(&mut self, path: P) -> io::Result<usize> {
+        let content = std::fs::read_to_string(path)?;
+        let candidates = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'));
+        Ok(self.resolve_paths(candidates))
     }
 
     fn parse_paths(directories: &[DirectoryInfo], dir_data: &[u8], files: &mut HashMap<u64, FileInfo>, hash_algo: HashAlgorithm) {
@@ -205,65 +313,9 @@ impl Index {
                     // File Mode
                     let path_str = String::from_utf8_lossy(&full_path_bytes).to_string();
 
-                    match hash_algo {
-                        HashAlgorithm::Murmur64A => {
-                             let hash = murmur_hash64a(&full_path_bytes);
-                             if let Some(f) = files.get_mut(&hash) { 
-                                 f.path = path_str;
-                             }
-                        },
-                        HashAlgorithm::Fnv1a => {
-                             // FNV1a usually expects lowercase in old GGPK? 
-                             // LibGGPK3 says: "NameHash(utf8Name) ... utf8Name must be lowercased unless it comes from ggpk before patch 3.21.2"
-                             // But wait, older GGPK (FNV) used various casing?
-                             // Actually LibGGPK3 code:
-                             // case 0x07E47507B4A92E53: return FNV1a64Hash(utf8Name);
-                             // AND NameHash(name) calls name.ToLowerInvariant() first if directory hash matches recent Murmur magic.
-                             // Wait, if it is FNV (else branch), it allocates stackalloc byte[name.Length] ... NO, it uses original casing?
-                             
-                             // LibGGPK3 logic:
-                             // If root_hash == MURMUR_MAGIC: lower case it, then hash.
-                             // Else: use original name to hash?
-                             
-                             // Let's look at `NameHash(scoped ReadOnlySpan<char> name)` in Index.cs again.
-                             /*
-                                if (_Directories[0].PathHash == 0xF42A94E69CFF42FEul) { // Murmur
-                                    name.ToLowerInvariant(span);
-                                    return MurmurHash64A(...);
-                                } else {
-                                    return NameHash(utf8... original); -> which uses FNV1a64Hash
-                                }
-                             */
-                             
-                             // So for FNV, we try original casing?
-                             // But my previous code tried all 4 combinations.
-                             // Let's try Original first, then Lower?
-                             // Optimization: Only compute FNV.
-                             
-                             let hash = fnv1a64(&full_path_bytes);
-                             if let Some(f) = files.get_mut(&hash) {
-                                 f.path = path_str.clone();
-                             } else {
-                                 // Fallback to lower?
-                                 let lower_bytes = full_path_bytes.to_ascii_lowercase();
-                                 let hash_lower = fnv1a64(&lower_bytes);
-                                 if let Some(f) = files.get_mut(&hash_lower) {
-                                     f.path = path_str;
-                                 }
-                             }
-                        },
-                        HashAlgorithm::Unknown => {
-                            // Fallback to trying everything (old slow behavior)
-                            let lower_bytes = full_path_bytes.to_ascii_lowercase();
-                            let hash_murmur = murmur_hash64a(&full_path_bytes);
-                            let hash_murmur_lower = murmur_hash64a(&lower_bytes);
-                            let hash_fnv = fnv1a64(&full_path_bytes);
-                            let hash_fnv_lower = fnv1a64(&lower_bytes);
-                            
-                            if let Some(f) = files.get_mut(&hash_murmur) { f.path = path_str; }
-                            else if let Some(f) = files.get_mut(&hash_murmur_lower) { f.path = path_str; }
-                            else if let Some(f) = files.get_mut(&hash_fnv) { f.path = path_str; }
-                            else if let Some(f) = files.get_mut(&hash_fnv_lower) { f.path = path_str; }
+                    if let Some(hash) = hash_algo.find_hash(files, &full_path_bytes) {
+                        if let Some(f) = files.get_mut(&hash) {
+                            f.path = path_str;
                         }
                     }
                 }
@@ -355,5 +407,52 @@ fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
     Ok(LittleEndian::read_u64(&buf))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn file(path_hash: u64) -> FileInfo {
+        FileInfo { path_hash, bundle_index: 0, file_offset: 0, file_size: 0, path: String::new() }
+    }
 
+    #[test]
+    fn test_resolve_paths() {
+        let known = murmur_hash64a(b"data/mods.datc64");
+        let other = murmur_hash64a(b"data/stats.datc64");
+        let mut index = Index {
+            bundles: Vec::new(),
+            files: [(known, file(known)), (other, file(other))].into_iter().collect(),
+            directories: Vec::new(),
+            hash_algorithm: HashAlgorithm::Murmur64A,
+        };
+        assert_eq!(index.unresolved_files().len(), 2);
+
+        let resolved = index.resolve_paths(["Data/Mods.datc64", "data/missing.datc64"]);
+        assert_eq!(resolved, 1);
+        assert_eq!(index.files[&known].path, "Data/Mods.datc64");
+
+        let unresolved = index.unresolved_files();
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].path_hash, other);
+    }
+
+    #[test]
+    fn test_hash_directory() {
+        // Root directory hashes as found in real indexes (see Index::read's algorithm detection)
+        assert_eq!(HashAlgorithm::Murmur64A.hash_directory(""), 0xF42A94E69CFF42FE);
+        assert_eq!(HashAlgorithm::Fnv1a.hash_directory(""), 0x07E47507B4A92E53);
+
+        assert_eq!(HashAlgorithm::Murmur64A.hash_directory("Art/Models"), murmur_hash64a(b"art/models"));
+        assert_eq!(HashAlgorithm::Fnv1a.hash_directory("Art/Models"), fnv1a64(b"Art/Models++"));
+        assert_ne!(HashAlgorithm::Fnv1a.hash_directory("Art/Models"), HashAlgorithm::Fnv1a.hash_directory("art/models"));
+    }
+
+    #[test]
+    fn test_find_hash_casing() {
+        let lower = fnv1a64(b"data/mods.dat");
+        let files: HashMap<u64, FileInfo> = [(lower, file(lower))].into_iter().collect();
+        assert_eq!(HashAlgorithm::Fnv1a.find_hash(&files, b"Data/Mods.dat"), Some(lower));
+        assert_eq!(HashAlgorithm::Unknown.find_hash(&files, b"data/mods.dat"), Some(lower));
+        assert_eq!(HashAlgorithm::Murmur64A.find_hash(&files, b"Data/Mods.dat"), None);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod bundle;
+pub mod cache;
+pub mod diff;
 pub mod index;
+pub mod paths;
+pub mod tree;
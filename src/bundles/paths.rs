@@ -0,0 +1,99 @@
+use super::index::{FileInfo, Index};
+
+/// Resolved bundle paths in sorted order, for prefix, extension and glob queries.
+/// Matching ignores ASCII case; results come back in path order.
+pub struct PathIndex<'a> {
+    entries: Vec<(String, &'a FileInfo)>, // Lowercased path, file
+}
+
+impl<'a> PathIndex<'a> {
+    pub fn build(index: &'a Index) -> Self {
+        let mut entries: Vec<(String, &'a FileInfo)> = index.files
+            .values()
+            .filter(|f| !f.path.is_empty())
+            .map(|f| (f.path.to_ascii_lowercase(), f))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.path.cmp(&b.1.path)));
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a FileInfo> + '_ {
+        self.entries.iter().map(|(_, f)| *f)
+    }
+
+    /// All files whose path starts with `prefix`, e.g. `Data/` or `Art/2DArt/`.
+    pub fn with_prefix(&self, prefix: &str) -> Vec<&'a FileInfo> {
+        self.prefix_range(&prefix.to_ascii_lowercase()).iter().map(|(_, f)| *f).collect()
+    }
+
+    /// All files with the given extension, with or without the leading dot (`datc64`, `.dds`).
+    pub fn with_extension(&self, extension: &str) -> Vec<&'a FileInfo> {
+        let suffix = format!(".{}", extension.trim_start_matches('.').to_ascii_lowercase());
+        self.entries.iter().filter(|(p, _)| p.ends_with(&suffix)).map(|(_, f)| *f).collect()
+    }
+
+    /// All files matching a glob pattern. `*` and `?` stay within one path segment, `**` spans directories.
+    pub fn glob(&self, pattern: &str) -> Vec<&'a FileInfo> {
+        let pattern = pattern.to_ascii_lowercase();
+        // Only the literal part before the first wildcard can narrow the sorted range
+        let literal_len = pattern.find(['*', '?']).unwrap_or(pattern.len());
+        self.prefix_range(&pattern[..literal_len])
+            .iter()
+            .filter(|(p, _)| glob_match(pattern.as_bytes(), p.as_bytes()))
+            .map(|(_, f)| *f)
+            .collect()
+    }
+
+    fn prefix_range(&self, prefix: &str) -> &[(String, &'a FileInfo)] {
+        let start = self.entries.partition_point(|(p, _)| p.as_str() < prefix);
+        let len = self.entries[start..].partition_point(|(p, _)| p.starts_with(prefix));
+        &self.entries[start..start + len]
+    }
+}
+
+/// Matches `path` against a glob `pattern`. Both are compared byte-wise, so callers lowercase them first.
+pub fn glob_match(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern.first() {
+        None => path.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            if let Some(after) = rest.strip_prefix(b"/") {
+                // `**/` matches zero or more whole directories
+                glob_match(after, path)
+                    || path.iter().enumerate().any(|(i, &c)| c == b'/' && glob_match(after, &path[i + 1..]))
+            } else {
+                (0..=path.len()).any(|i| glob_match(rest, &path[i..]))
+            }
+        },
+        Some(b'*') => {
+            let segment_end = path.iter().position(|&c| c == b'/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| glob_match(&pattern[1..], &path[i..]))
+        },
+        Some(b'?') => !path.is_empty() && path[0] != b'/' && glob_match(&pattern[1..], &path[1..]),
+        Some(&c) => path.first() == Some(&c) && glob_match(&pattern[1..], &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"data/*.datc64", b"data/mods.datc64"));
+        assert!(!glob_match(b"data/*.datc64", b"data/french/mods.datc64"));
+        assert!(glob_match(b"data/**/*.datc64", b"data/mods.datc64"));
+        assert!(glob_match(b"data/**/*.datc64", b"data/french/mods.datc64"));
+        assert!(glob_match(b"art/**", b"art/2dart/a.dds"));
+        assert!(glob_match(b"data/mod?.datc64", b"data/mods.datc64"));
+        assert!(!glob_match(b"data/mod?.datc64", b"data/mod.datc64"));
+    }
+}
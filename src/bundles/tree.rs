@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use serde::Serialize;
+use super::index::Index;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DirectoryNode {
+    pub name: String,
+    pub path: String,
+    pub directories: BTreeMap<String, DirectoryNode>,
+    pub files: BTreeMap<String, u64>, // Name -> path hash
+    pub file_count: usize, // Recursive
+    pub total_size: u64, // Recursive sum of uncompressed file sizes
+    pub recursive_size: Option<u32>, // From the index directory record, if its hash was matched
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleTree {
+    pub root: DirectoryNode,
+}
+
+impl BundleTree {
+    /// Builds the tree from every file with a resolved path. Unresolved files are left out.
+    pub fn build(index: &Index) -> Self {
+        let mut root = DirectoryNode::default();
+
+        for f in index.files.values() {
+            if f.path.is_empty() { continue; }
+
+            let mut parts: Vec<&str> = f.path.split('/').collect();
+            let name = parts.pop().unwrap_or_default();
+
+            let mut node = &mut root;
+            for part in parts {
+                let path = if node.path.is_empty() { part.to_string() } else { format!("{}/{}", node.path, part) };
+                node = node.directories.entry(part.to_string()).or_insert_with(|| DirectoryNode {
+                    name: part.to_string(),
+                    path,
+                    ..Default::default()
+                });
+            }
+            node.files.insert(name.to_string(), f.path_hash);
+        }
+
+        // Unnamed directory records have an empty path too, so the root is matched by hash instead
+        let recursive_sizes: HashMap<String, u32> = index.directories
+            .iter()
+            .filter(|d| !d.path.is_empty())
+            .map(|d| (d.path.to_ascii_lowercase(), d.recursive_size))
+            .collect();
+        let file_sizes: HashMap<u64, u32> = index.files.values().map(|f| (f.path_hash, f.file_size)).collect();
+        root.compute_totals(&file_sizes, &recursive_sizes);
+
+        let root_hash = index.hash_algorithm.hash_directory("");
+        root.recursive_size = index.directories.iter().find(|d| d.path_hash == root_hash).map(|d| d.recursive_size);
+
+        Self { root }
+    }
+
+    /// Finds a directory by path, ignoring ASCII case. An empty path is the root.
+    pub fn find(&self, path: &str) -> Option<&DirectoryNode> {
+        let mut node = &self.root;
+        for part in path.split('/') {
+            if part.is_empty() { continue; }
+            node = node.directories.values().find(|d| d.name.eq_ignore_ascii_case(part))?;
+        }
+        Some(node)
+    }
+
+    /// Lists a directory as `DIR:name` and `FILE:name` entries, like `GgpkReader::list_files_in_directory`.
+    pub fn list_files_in_directory(&self, path: &str) -> io::Result<Vec<String>> {
+        let dir = self.find(path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Directory {} not found", path)))?;
+
+        let mut entries = Vec::with_capacity(dir.directories.len() + dir.files.len());
+        entries.extend(dir.directories.keys().map(|name| format!("DIR:{}", name)));
+        entries.extend(dir.files.keys().map(|name| format!("FILE:{}", name)));
+        Ok(entries)
+    }
+}
+
+impl DirectoryNode {
+    fn compute_totals(&mut self, file_sizes: &HashMap<u64, u32>, recursive_sizes: &HashMap<String, u32>) {
+        self.file_count = self.files.len();
+        self.total_size = self.files.values().map(|h| file_sizes.get(h).copied().unwrap_or(0) as u64).sum();
+        self.recursive_size = recursive_sizes.get(&self.path.to_ascii_lowercase()).copied();
+
+        for child in self.directories.values_mut() {
+            child.compute_totals(file_sizes, recursive_sizes);
+            self.file_count += child.file_count;
+            self.total_size += child.total_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundles::index::{DirectoryInfo, FileInfo, HashAlgorithm};
+
+    fn directory(path_hash: u64, recursive_size: u32) -> DirectoryInfo {
+        DirectoryInfo { path_hash, offset: 0, size: 0, recursive_size, path: String::new() }
+    }
+
+    #[test]
+    fn test_bundle_tree() {
+        let files = [("Data/Mods.datc64", 10), ("Data/Stats.datc64", 20), ("Art/2DArt/a.dds", 5)]
+            .iter()
+            .enumerate()
+            .map(|(i, (path, size))| (i as u64, FileInfo {
+                path_hash: i as u64,
+                bundle_index: 0,
+                file_offset: 0,
+                file_size: *size,
+                path: path.to_string(),
+            }))
+            .collect();
+        let index = Index { bundles: Vec::new(), files, directories: Vec::new(), hash_algorithm: HashAlgorithm::Murmur64A };
+
+        let tree = index.tree();
+        assert_eq!(tree.root.file_count, 3);
+        assert_eq!(tree.root.total_size, 35);
+        assert_eq!(tree.list_files_in_directory("").unwrap(), vec!["DIR:Art", "DIR:Data"]);
+        assert_eq!(tree.list_files_in_directory("data").unwrap(), vec!["FILE:Mods.datc64", "FILE:Stats.datc64"]);
+        assert_eq!(tree.find("Art/2DArt").unwrap().path, "Art/2DArt");
+        assert!(tree.list_files_in_directory("Audio").is_err());
+    }
+
+    #[test]
+    fn test_directory_records() {
+        let algo = HashAlgorithm::Murmur64A;
+        let files: HashMap<u64, FileInfo> = [("Data/Mods.datc64", 10), ("Art/2DArt/a.dds", 5)]
+            .iter()
+            .enumerate()
+            .map(|(i, (path, size))| (i as u64, FileInfo { path_hash: i as u64, bundle_index: 0, file_offset: 0, file_size: *size, path: path.to_string() }))
+            .collect();
+        let mut directories = vec![
+            directory(0xDEAD, 999), // matches no path, stays unnamed
+            directory(algo.hash_directory("Data"), 10),
+            directory(algo.hash_directory("Art/2DArt"), 5),
+            directory(algo.hash_directory(""), 15),
+            directory(algo.hash_directory("Art"), 5),
+        ];
+        Index::assign_directory_paths(&mut directories, &files, algo);
+        assert_eq!(directories.iter().map(|d| d.path.as_str()).collect::<Vec<_>>(), vec!["", "Data", "Art/2DArt", "", "Art"]);
+
+        let index = Index { bundles: Vec::new(), files, directories, hash_algorithm: algo };
+        let tree = index.tree();
+        assert_eq!(tree.root.recursive_size, Some(15));
+        assert_eq!(tree.find("data").unwrap().recursive_size, Some(10));
+        assert_eq!(tree.find("Art/2DArt").unwrap().recursive_size, Some(5));
+    }
+}
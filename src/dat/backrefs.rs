@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use super::reader::{DatReader, DatValue};
+use super::schema::Table;
+use super::types::ColumnType;
+use crate::versioned;
+
+pub const BACKREFS_MAGIC: [u8; 4] = *b"EGRR";
+/// Bump whenever the serialized layout of `ReverseIndex` changes.
+pub const BACKREFS_FORMAT_VERSION: u32 = 1;
+
+/// A row that points at another row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Referrer<'a> {
+    pub table: &'a str,
+    pub column: &'a str,
+    pub row: u32,
+}
+
+/// Maps every (table, row) to the rows whose foreign keys point at it.
+///
+/// Built from the schema columns with a `references` to a row index, plus `row` columns
+/// referencing their own table. References by key value (`references.column`) are not followed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ReverseIndex {
+    /// Free-form label of what the index was built from, e.g. the game version.
+    pub version: String,
+    sources: Vec<(String, String)>, // (table, column) of each referencing column
+    targets: HashMap<String, HashMap<u32, Vec<(u32, u32)>>>, // table -> row -> (source, row)
+}
+
+impl ReverseIndex {
+    pub fn build<'a, I>(tables: I, version: &str) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = (&'a DatReader<'a>, &'a Table)>,
+    {
+        let mut index = Self { version: version.to_string(), ..Self::default() };
+        for (reader, table) in tables {
+            index.add_table(reader, table)?;
+        }
+        index.sort();
+        Ok(index)
+    }
+
+    fn add_table(&mut self, reader: &DatReader<'_>, table: &Table) -> io::Result<()> {
+        let view = reader.view(table)?;
+        for (index, col) in table.columns.iter().enumerate() {
+            let target = match &col.references {
+                Some(r) if r.column.is_none() => r.table.as_str(),
+                None if view.layout().specs[index].ty == ColumnType::Row => table.name.as_str(),
+                _ => continue,
+            };
+            let source = self.sources.len() as u32;
+            self.sources.push((table.name.clone(), col.display_name(index)));
+
+            let rows = self.targets.entry(target.to_string()).or_default();
+            for row in view.rows() {
+                let values = match row.get(index).into_owned() {
+                    DatValue::List(count, offset) => reader.read_list_values(offset, count, col)?,
+                    value => vec![value],
+                };
+                for value in values {
+                    if let DatValue::ForeignRow(r) | DatValue::ForeignKey(r, _) = value {
+                        rows.entry(r as u32).or_default().push((source, row.index()));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sort(&mut self) {
+        for rows in self.targets.values_mut() {
+            for referrers in rows.values_mut() {
+                referrers.sort_unstable();
+                referrers.dedup();
+            }
+        }
+    }
+
+    /// Every row pointing at `row` of `table`, ordered by referencing column then row.
+    pub fn referrers(&self, table: &str, row: u32) -> Vec<Referrer<'_>> {
+        let Some(referrers) = self.targets.get(table).and_then(|rows| rows.get(&row)) else {
+            return Vec::new();
+        };
+        referrers.iter()
+            .map(|&(source, row)| {
+                let (table, column) = &self.sources[source as usize];
+                Referrer { table, column, row }
+            })
+            .collect()
+    }
+
+    /// Number of (table, row) pairs that are referenced at least once.
+    pub fn len(&self) -> usize {
+        self.targets.values().map(HashMap::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Layout: `BACKREFS_MAGIC`, format version (u32 LE), then the bincode of the index.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        versioned::write_header(&mut writer, BACKREFS_MAGIC, BACKREFS_FORMAT_VERSION)?;
+        versioned::serialize_into(&mut writer, self)?;
+        writer.flush()
+    }
+
+    /// Loads a saved index; compare `version` to tell whether it is still current.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let limit = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        versioned::read_header(&mut reader, BACKREFS_MAGIC, BACKREFS_FORMAT_VERSION, "a reverse reference index")?;
+        versioned::deserialize_from(reader, limit)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Unreadable reverse reference index: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, dat, reference, table, TempDir};
+
+    #[test]
+    fn test_reverse_index() {
+        let stats = table("Stats", vec![column("Id", "string")]);
+        let mods = table("Mods", vec![
+            Column { references: reference("Stats"), ..column("Stat1", "foreignrow") },
+            Column { array: true, references: reference("Stats"), ..column("Stats", "foreignrow") },
+        ]);
+        let stats_reader = dat(&stats, vec![vec![DatValue::String("a".to_string())], vec![DatValue::String("b".to_string())]]);
+        let mods_reader = dat(&mods, vec![
+            vec![DatValue::ForeignKey(1, 0), DatValue::Array(vec![DatValue::ForeignKey(0, 0), DatValue::ForeignKey(1, 0)])],
+            vec![DatValue::Null, DatValue::Array(vec![DatValue::ForeignKey(1, 0)])],
+        ]);
+
+        let index = ReverseIndex::build([(&stats_reader, &stats), (&mods_reader, &mods)], "3.25").unwrap();
+        assert_eq!(index.referrers("Stats", 1), vec![
+            Referrer { table: "Mods", column: "Stat1", row: 0 },
+            Referrer { table: "Mods", column: "Stats", row: 0 },
+            Referrer { table: "Mods", column: "Stats", row: 1 },
+        ]);
+        assert_eq!(index.len(), 2);
+        assert!(index.referrers("Mods", 0).is_empty());
+
+        let dir = TempDir::new("backrefs");
+        let path = dir.path().join("backrefs.bin");
+        index.save(&path).unwrap();
+        let loaded = ReverseIndex::load(&path).unwrap();
+        assert_eq!(loaded.version, "3.25");
+        assert_eq!(loaded.referrers("Stats", 0), index.referrers("Stats", 0));
+
+        // A corrupt length prefix after a valid header is an error, not a huge allocation
+        let mut data = Vec::new();
+        versioned::write_header(&mut data, BACKREFS_MAGIC, BACKREFS_FORMAT_VERSION).unwrap();
+        data.extend_from_slice(&0x7FFF_FFFF_FFFFu64.to_le_bytes());
+        fs::write(&path, &data).unwrap();
+        assert_eq!(ReverseIndex::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,166 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use super::format::DatFormat;
+use super::view::DatStr;
+
+const MARKER: [u8; 8] = [0xBB; 8];
+const SAMPLE_ROWS: usize = 64;
+
+/// A possible start of the variable data section, see [`boundary_candidates`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BoundaryCandidate {
+    pub data_section_offset: u64,
+    pub row_length: usize,
+    /// `row_length` equals the row width the caller expected.
+    pub matches_hint: bool,
+    /// Pointer-sized row values that land on the start of a well-formed string.
+    pub string_hits: usize,
+    /// The variable section is only the marker, or a row value points at its first byte.
+    pub plausible_data_section: bool,
+}
+
+/// Every offset where a data section could start: an `0xBB` x 8 marker placed so that the bytes
+/// before it split evenly into `row_count` rows. Best first: rows of the hinted width, then the
+/// most values that read as string offsets, then a plausible data section, then the earliest.
+///
+/// Without any marker the hinted position itself is offered, so that a file whose marker was
+/// damaged can still be read when the schema is known.
+pub fn boundary_candidates(data: &[u8], row_count: u32, format: DatFormat, width_hint: Option<usize>) -> Vec<BoundaryCandidate> {
+    if row_count == 0 {
+        return Vec::new();
+    }
+    let rows = row_count as usize;
+    let mut offsets: Vec<usize> = Vec::new();
+
+    // The hinted position first, then every offset ending a whole number of rows
+    if let Some(width) = width_hint {
+        let expected = 4 + rows * width;
+        if data.get(expected..expected + MARKER.len()) == Some(&MARKER) {
+            offsets.push(expected);
+        }
+    }
+    offsets.extend(
+        (4..data.len().saturating_sub(MARKER.len() - 1)).step_by(rows)
+            .filter(|&i| data[i..i + MARKER.len()] == MARKER && Some((i - 4) / rows) != width_hint)
+    );
+    if offsets.is_empty() {
+        if let Some(width) = width_hint {
+            let expected = 4 + rows * width;
+            if expected <= data.len() {
+                offsets.push(expected);
+            }
+        }
+    }
+
+    let mut candidates: Vec<BoundaryCandidate> = offsets.into_iter()
+        .map(|offset| {
+            let row_length = (offset - 4) / rows;
+            BoundaryCandidate {
+                data_section_offset: offset as u64,
+                row_length,
+                matches_hint: width_hint == Some(row_length),
+                string_hits: string_hits(data, offset, row_length, rows, format),
+                plausible_data_section: plausible_data_section(data, offset, row_length, rows, format),
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.matches_hint.cmp(&a.matches_hint)
+        .then(b.string_hits.cmp(&a.string_hits))
+        .then(b.plausible_data_section.cmp(&a.plausible_data_section))
+        .then(a.data_section_offset.cmp(&b.data_section_offset)));
+    candidates
+}
+
+// With the right boundary string columns line up and point at string starts in every row;
+// with a wrong one the rows are sheared and they mostly do not.
+fn string_hits(data: &[u8], offset: usize, row_length: usize, rows: usize, format: DatFormat) -> usize {
+    let pointer = format.pointer_size();
+    let unit = format.encoding().unit_size();
+    let var = &data[offset..];
+    if row_length < pointer || var.len() <= MARKER.len() {
+        return 0;
+    }
+
+    let mut hits = 0;
+    for row in (0..rows.min(SAMPLE_ROWS)).map(|r| &data[4 + r * row_length..4 + (r + 1) * row_length]) {
+        for o in 0..=row_length - pointer {
+            let value = if pointer == 8 {
+                LittleEndian::read_u64(&row[o..o + 8])
+            } else {
+                LittleEndian::read_u32(&row[o..o + 4]) as u64
+            } as usize;
+            if value < MARKER.len() || value >= var.len() {
+                continue;
+            }
+            // A string starts right after the marker or right after another string's terminator
+            let starts = value == MARKER.len() || var[value - unit..value].iter().all(|&b| b == 0);
+            let s = DatStr::at_with(var, value, format.encoding());
+            let end = value + s.as_bytes().len();
+            let terminated = end + unit <= var.len();
+            if starts && terminated && !s.chars().any(|c| c == char::REPLACEMENT_CHARACTER || (c.is_control() && c != '\n')) {
+                hits += 1;
+            }
+        }
+    }
+    hits
+}
+
+// Tables without strings or arrays have nothing after the marker. Otherwise the first value after
+// the marker belongs to some row, so a row holds its offset; a boundary found inside the rows
+// leaves row bytes there instead, which nothing points at.
+fn plausible_data_section(data: &[u8], offset: usize, row_length: usize, rows: usize, format: DatFormat) -> bool {
+    if data.len() <= offset + MARKER.len() {
+        return true;
+    }
+    let pointer = format.pointer_size();
+    if row_length < pointer {
+        return false;
+    }
+    let first = MARKER.len() as u64;
+    data[4..offset].chunks_exact(row_length).take(rows).any(|row| (0..=row_length - pointer).any(|o| {
+        let value = if pointer == 8 { LittleEndian::read_u64(&row[o..]) } else { LittleEndian::read_u32(&row[o..]) as u64 };
+        value == first
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundary_candidates() {
+        // Two rows of (i64 holding a marker, string) so an aligned 0xBB run appears inside the rows
+        let mut data = 2u32.to_le_bytes().to_vec();
+        for _ in 0..2 {
+            data.extend_from_slice(&[0xBB; 8]);
+            data.extend_from_slice(&8u64.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xBB; 8]);
+        data.extend("Mods".encode_utf16().chain([0, 0]).flat_map(u16::to_le_bytes));
+
+        let candidates = boundary_candidates(&data, 2, DatFormat::Dat64, None);
+        assert!(candidates.len() > 1);
+        assert_eq!(candidates[0].data_section_offset, 36);
+        assert_eq!(candidates[0].row_length, 16);
+
+        // A hint wins over string hits
+        let hinted = boundary_candidates(&data, 2, DatFormat::Dat64, Some(8));
+        assert_eq!(hinted[0].row_length, 8);
+        assert!(hinted[0].matches_hint);
+    }
+
+    #[test]
+    fn test_boundary_without_strings() {
+        // Two rows of (i64 holding a marker, i64) and nothing after the real marker
+        let mut data = 2u32.to_le_bytes().to_vec();
+        for _ in 0..2 {
+            data.extend_from_slice(&[0xBB; 8]);
+            data.extend_from_slice(&7u64.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xBB; 8]);
+
+        let candidates = boundary_candidates(&data, 2, DatFormat::Dat64, None);
+        assert_eq!(candidates.iter().map(|c| c.data_section_offset).collect::<Vec<_>>(), vec![36, 4, 20]);
+        assert!(candidates[0].plausible_data_section);
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+use super::schema::{Column, Enumeration, Schema, Table};
+use super::types::{ColumnSpec, ColumnType};
+
+/// Generates Rust source for typed access to `schema`: one struct per table implementing
+/// `FromDatRow`, and one enum per enumeration.
+///
+/// Meant to be run from a build script and `include!`d; the output refers to this crate as
+/// `crate_path` (usually `exile_ggpk`) and derives `serde::Serialize`, so the including crate
+/// needs `serde` as a dependency. Tables with a column type outside the known vocabulary are
+/// skipped with a comment.
+pub fn generate(schema: &Schema, crate_path: &str) -> String {
+    let tables: HashSet<&str> = schema.tables.iter().map(|t| t.name.as_str()).collect();
+    let enums: HashSet<&str> = schema.enumeration.iter().flatten().map(|e| e.name.as_str()).collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "// Generated from dat-schema version {}. Do not edit.", schema.version);
+    let _ = writeln!(out, "#[allow(unused_imports)]");
+    let _ = writeln!(out, "use {}::dat::typed::{{take, variant, FromDatRow, FromDatValue, RowIndex, RowRef}};", crate_path);
+    let _ = writeln!(out, "#[allow(unused_imports)]");
+    let _ = writeln!(out, "use {}::dat::reader::DatValue;", crate_path);
+
+    for enumeration in schema.enumeration.iter().flatten() {
+        out.push('\n');
+        out.push_str(&generate_enum(enumeration));
+    }
+    for table in &schema.tables {
+        out.push('\n');
+        match generate_table(table, &tables, &enums) {
+            Ok(code) => out.push_str(&code),
+            Err(e) => { let _ = writeln!(out, "// Skipped {}: {}", table.name, e); },
+        }
+    }
+    out
+}
+
+/// [`generate`] into `path`, leaving the file untouched when the output is unchanged so cargo
+/// does not rebuild needlessly.
+pub fn write_module(schema: &Schema, crate_path: &str, path: impl AsRef<Path>) -> io::Result<()> {
+    let code = generate(schema, crate_path);
+    if fs::read_to_string(path.as_ref()).is_ok_and(|existing| existing == code) {
+        return Ok(());
+    }
+    fs::write(path, code)
+}
+
+fn generate_enum(enumeration: &Enumeration) -> String {
+    let name = type_ident(&enumeration.name);
+    let mut seen = HashSet::new();
+    let variants: Vec<String> = enumeration.enumerators.iter().enumerate()
+        .map(|(i, e)| {
+            let ident = type_ident(e);
+            if ident.is_empty() || !seen.insert(ident.clone()) { format!("Unknown{}", i) } else { ident }
+        })
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]");
+    let _ = writeln!(out, "pub enum {} {{", name);
+    for v in &variants {
+        let _ = writeln!(out, "    {},", v);
+    }
+    let _ = writeln!(out, "}}\n");
+    let _ = writeln!(out, "impl FromDatValue for {} {{", name);
+    let _ = writeln!(out, "    fn from_dat_value(value: DatValue) -> std::io::Result<Self> {{");
+    let list: Vec<String> = variants.iter().map(|v| format!("{}::{}", name, v)).collect();
+    let _ = writeln!(out, "        variant(&[{}], value, \"{}\")", list.join(", "), name);
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+    out
+}
+
+fn generate_table(table: &Table, tables: &HashSet<&str>, enums: &HashSet<&str>) -> io::Result<String> {
+    let name = type_ident(&table.name);
+    let mut fields = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, col) in table.columns.iter().enumerate() {
+        let ty = field_type(col, &name, tables, enums)?;
+        let mut field = col.name.as_deref().map(field_ident).unwrap_or_default();
+        if field.is_empty() || !seen.insert(field.clone()) {
+            field = format!("unknown{}", i);
+        }
+        fields.push((field, ty, col.name.clone().unwrap_or_default()));
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "#[derive(Debug, Clone, PartialEq, serde::Serialize)]");
+    let _ = writeln!(out, "pub struct {} {{", name);
+    for (field, ty, _) in &fields {
+        let _ = writeln!(out, "    pub {}: {},", field, ty);
+    }
+    let _ = writeln!(out, "}}\n");
+    let _ = writeln!(out, "impl FromDatRow for {} {{", name);
+    let _ = writeln!(out, "    const TABLE: &'static str = {:?};", table.name);
+    let columns: Vec<String> = fields.iter().map(|(_, _, column)| format!("{:?}", column)).collect();
+    let _ = writeln!(out, "    const COLUMNS: &'static [&'static str] = &[{}];\n", columns.join(", "));
+    let _ = writeln!(out, "    fn from_row(values: Vec<DatValue>) -> std::io::Result<Self> {{");
+    if fields.is_empty() {
+        let _ = writeln!(out, "        let _ = values;");
+    } else {
+        let _ = writeln!(out, "        let mut values = values.into_iter();");
+    }
+    let _ = writeln!(out, "        Ok(Self {{");
+    for (field, _, column) in &fields {
+        let _ = writeln!(out, "            {}: take(&mut values, {:?})?,", field, column);
+    }
+    let _ = writeln!(out, "        }})");
+    let _ = writeln!(out, "    }}");
+    let _ = writeln!(out, "}}");
+    Ok(out)
+}
+
+fn field_type(col: &Column, table: &str, tables: &HashSet<&str>, enums: &HashSet<&str>) -> io::Result<String> {
+    let spec = ColumnSpec::from_column(col)?;
+    let target = col.references.as_ref().map(|r| r.table.as_str());
+    let element = match spec.ty {
+        ColumnType::Bool => "bool".to_string(),
+        ColumnType::U8 => "u8".to_string(),
+        ColumnType::I16 => "i16".to_string(),
+        ColumnType::U16 => "u16".to_string(),
+        ColumnType::I32 => "i32".to_string(),
+        ColumnType::U32 => "u32".to_string(),
+        ColumnType::I64 => "i64".to_string(),
+        ColumnType::U64 => "u64".to_string(),
+        ColumnType::F32 => "f32".to_string(),
+        ColumnType::String => "String".to_string(),
+        ColumnType::Row => format!("Option<RowRef<{}>>", table),
+        ColumnType::ForeignRow => match target {
+            Some(t) if tables.contains(t) => format!("Option<RowRef<{}>>", type_ident(t)),
+            _ => "Option<RowIndex>".to_string(),
+        },
+        ColumnType::EnumRow => match target {
+            Some(t) if enums.contains(t) => format!("Option<{}>", type_ident(t)),
+            _ => "Option<usize>".to_string(),
+        },
+        ColumnType::Rid => "Option<RowIndex>".to_string(),
+        ColumnType::Unknown => "()".to_string(),
+    };
+    Ok(match (spec.array, spec.interval) {
+        (true, _) => format!("Vec<{}>", element),
+        (_, true) => format!("({0}, {0})", element),
+        _ => element,
+    })
+}
+
+/// `PascalCase` identifier from a schema name, dropping characters Rust does not allow.
+fn type_ident(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = true;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(if upper { c.to_ascii_uppercase() } else { c });
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+/// `snake_case` field name from a `PascalCase` column name, e.g. `ModTypeKey` -> `mod_type_key`.
+fn field_ident(name: &str) -> String {
+    let chars: Vec<char> = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+    let mut out = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let prev = i.checked_sub(1).map(|p| chars[p]);
+            let next = chars.get(i + 1);
+            // Word boundary: aB, 1B, or the B of ABc
+            let boundary = prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+                || (prev.is_some_and(|p| p.is_ascii_uppercase()) && next.is_some_and(|n| n.is_ascii_lowercase()));
+            if boundary && !out.ends_with('_') {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    const KEYWORDS: &[&str] = &["as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+        "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "static", "struct",
+        "trait", "true", "type", "unsafe", "use", "where", "while", "async", "await", "dyn", "abstract", "become", "box",
+        "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual", "yield", "try"];
+    if KEYWORDS.contains(&out.as_str()) {
+        out.insert_str(0, "r#");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::reader::DatValue;
+    use crate::dat::typed::{RowIndex, RowRef};
+    use crate::testing::{column, dat, reference, table};
+
+    // `generate(&schema(), "crate")`, checked in so that the output is compiled
+    mod generated {
+        include!("testdata/generated.rs");
+    }
+
+    fn schema() -> Schema {
+        Schema {
+            version: 7,
+            created_at: 0,
+            tables: vec![
+                table("Mods", vec![
+                    column("Id", "string"),
+                    Column { array: true, references: reference("Tags"), ..column("Tags", "foreignrow") },
+                    column("ModTypeKey", "row"),
+                    column("Key", "rid"),
+                    Column { references: reference("Missing"), ..column("Stat", "foreignrow") },
+                    Column { references: reference("Rarity"), ..column("Rarity", "enumrow") },
+                    Column { interval: true, ..column("Level", "i32") },
+                ]),
+                table("Tags", vec![]),
+            ],
+            enumeration: Some(vec![Enumeration { name: "Rarity".to_string(), enumerators: vec!["Normal".to_string(), "Magic".to_string()] }]),
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let schema = schema();
+        assert_eq!(generate(&schema, "crate"), include_str!("testdata/generated.rs"));
+        assert_eq!(field_ident("HASH32"), "hash32");
+        assert_eq!(field_ident("IsUIElement"), "is_ui_element");
+        assert_eq!(field_ident("Type"), "r#type");
+
+        let table = &schema.tables[0];
+        let reader = dat(table, vec![vec![
+            DatValue::String("Strength1".to_string()),
+            DatValue::Array(vec![DatValue::ForeignKey(2, 0)]),
+            DatValue::Null,
+            DatValue::ForeignKey(1, 0),
+            DatValue::ForeignKey(4, 0),
+            DatValue::EnumRow(1),
+            DatValue::Interval(1, 10),
+        ]]);
+        let row: generated::Mods = reader.read_typed(0, table).unwrap();
+        assert_eq!(row, generated::Mods {
+            id: "Strength1".to_string(),
+            tags: vec![Some(RowRef::new(2))],
+            mod_type_key: None,
+            key: Some(RowIndex(1)),
+            stat: Some(RowIndex(4)),
+            rarity: Some(generated::Rarity::Magic),
+            level: (1, 10),
+        });
+
+        let mut renamed = table.clone();
+        renamed.columns[0].name = Some("Name".to_string());
+        assert!(reader.read_typed::<generated::Mods>(0, &renamed).is_err());
+    }
+}
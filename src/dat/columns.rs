@@ -0,0 +1,185 @@
+use std::io;
+use super::reader::DatValue;
+use super::schema::Column;
+use super::view::{DatStr, DatValueRef, TableView};
+
+/// Condition evaluated against one column of every row.
+#[derive(Debug, Clone)]
+pub enum Predicate {
+    Equals(DatValue),
+    /// Substring match on string columns.
+    Contains(String),
+    /// Inclusive numeric range; matches ints, longs, floats and row references.
+    Range(f64, f64),
+}
+
+impl Predicate {
+    pub fn matches(&self, value: &DatValueRef<'_>) -> bool {
+        match self {
+            Predicate::Equals(DatValue::String(expected)) => matches!(value, DatValueRef::String(s) if s.eq_str(expected)),
+            // `row` columns read as `ForeignRow`, `foreignrow` ones as `ForeignKey`; only the row counts
+            Predicate::Equals(DatValue::ForeignRow(row) | DatValue::ForeignKey(row, _)) =>
+                matches!(*value, DatValueRef::ForeignRow(r) | DatValueRef::ForeignKey(r, _) if r == *row),
+            Predicate::Equals(expected) => !matches!(value, DatValueRef::String(_)) && value.into_owned() == *expected,
+            Predicate::Contains(needle) => matches!(value, DatValueRef::String(s) if s.contains(needle)),
+            Predicate::Range(min, max) => !matches!(value, DatValueRef::String(_))
+                && number(&value.into_owned()).is_some_and(|v| v >= *min && v <= *max),
+        }
+    }
+
+    /// `matches` for an owned value, e.g. one reached through a join.
+    pub fn matches_value(&self, value: &DatValue) -> bool {
+        match self {
+            Predicate::Equals(DatValue::ForeignRow(row) | DatValue::ForeignKey(row, _)) =>
+                matches!(*value, DatValue::ForeignRow(r) | DatValue::ForeignKey(r, _) if r == *row),
+            Predicate::Equals(expected) => value == expected,
+            Predicate::Contains(needle) => matches!(value, DatValue::String(s) if s.contains(needle.as_str())),
+            Predicate::Range(min, max) => number(value).is_some_and(|v| v >= *min && v <= *max),
+        }
+    }
+}
+
+/// Ints, longs, floats and row references as a number, for ranges and ordering.
+pub(crate) fn number(value: &DatValue) -> Option<f64> {
+    match *value {
+        DatValue::Int(v) => Some(v as f64),
+        DatValue::Long(v) => Some(v as f64),
+        DatValue::Float(v) => Some(v as f64),
+        DatValue::ForeignRow(v) | DatValue::ForeignKey(v, _) | DatValue::EnumRow(v) => Some(v as f64),
+        _ => None,
+    }
+}
+
+/// A single column read across all rows at its fixed offset, without decoding the other columns.
+#[derive(Clone, Copy)]
+pub struct ColumnView<'v, 'r> {
+    view: &'v TableView<'r>,
+    index: usize,
+}
+
+impl<'v, 'r> ColumnView<'v, 'r> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn column(&self) -> &'r Column {
+        &self.view.table().columns[self.index]
+    }
+
+    /// Byte offset of the column within a row.
+    pub fn offset(&self) -> usize {
+        self.view.layout().offsets[self.index]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = DatValueRef<'r>> + 'v {
+        let (view, index) = (self.view, self.index);
+        view.rows().map(move |row| row.get(index))
+    }
+
+    pub fn to_vec(&self) -> Vec<DatValue> {
+        self.iter().map(DatValueRef::into_owned).collect()
+    }
+
+    pub fn ints(&self) -> impl Iterator<Item = Option<i64>> + 'v {
+        self.iter().map(|v| match v {
+            DatValueRef::Int(i) => Some(i),
+            DatValueRef::Long(l) => Some(l as i64),
+            _ => None,
+        })
+    }
+
+    pub fn floats(&self) -> impl Iterator<Item = Option<f32>> + 'v {
+        self.iter().map(|v| match v { DatValueRef::Float(f) => Some(f), _ => None })
+    }
+
+    pub fn bools(&self) -> impl Iterator<Item = Option<bool>> + 'v {
+        self.iter().map(|v| match v { DatValueRef::Bool(b) => Some(b), _ => None })
+    }
+
+    pub fn strings(&self) -> impl Iterator<Item = Option<DatStr<'r>>> + 'v {
+        self.iter().map(|v| match v { DatValueRef::String(s) => Some(s), _ => None })
+    }
+
+    /// Referenced row indices; `None` for nulls.
+    pub fn rows(&self) -> impl Iterator<Item = Option<usize>> + 'v {
+        self.iter().map(|v| match v {
+            DatValueRef::ForeignRow(r) | DatValueRef::ForeignKey(r, _) => Some(r),
+            _ => None,
+        })
+    }
+
+    /// Indices of the rows whose value satisfies `predicate`.
+    pub fn filter(&self, predicate: &Predicate) -> Vec<u32> {
+        self.filter_rows(0..self.view.row_count(), predicate)
+    }
+
+    fn filter_rows(&self, rows: impl IntoIterator<Item = u32>, predicate: &Predicate) -> Vec<u32> {
+        rows.into_iter()
+            .filter(|&i| self.view.row(i).is_some_and(|row| predicate.matches(&row.get(self.index))))
+            .collect()
+    }
+}
+
+impl<'r> TableView<'r> {
+    pub fn column(&self, index: usize) -> Option<ColumnView<'_, 'r>> {
+        (index < self.table().columns.len()).then_some(ColumnView { view: self, index })
+    }
+
+    pub fn column_by_name(&self, name: &str) -> Option<ColumnView<'_, 'r>> {
+        self.column_index(name).map(|index| ColumnView { view: self, index })
+    }
+
+    /// Rows matching every `(column name, predicate)` pair. Each condition only
+    /// re-reads its own column for the rows that survived the previous ones.
+    pub fn filter(&self, conditions: &[(&str, Predicate)]) -> io::Result<Vec<u32>> {
+        let mut rows: Vec<u32> = (0..self.row_count()).collect();
+        for (name, predicate) in conditions {
+            let column = self.column_by_name(name)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} not in table {}", name, self.table().name)))?;
+            rows = column.filter_rows(rows, predicate);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::format::StringEncoding;
+    use crate::dat::reader::DatReader;
+    use crate::testing::{column, table};
+
+    #[test]
+    fn test_column_filter() {
+        // Rows of (Domain: i32, Level: i32)
+        let mut data = Vec::new();
+        data.extend_from_slice(&4u32.to_le_bytes());
+        for (domain, level) in [(1i32, 10i32), (2, 20), (1, 30), (1, 40)] {
+            data.extend_from_slice(&domain.to_le_bytes());
+            data.extend_from_slice(&level.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xBB; 8]);
+
+        let table = table("Mods", vec![column("Domain", "i32"), column("Level", "i32")]);
+        let reader = DatReader::new(data, "Mods.datc64").unwrap();
+        let view = reader.view(&table).unwrap();
+
+        let domain = view.column_by_name("Domain").unwrap();
+        assert_eq!(domain.offset(), 0);
+        assert_eq!(domain.ints().collect::<Vec<_>>(), vec![Some(1), Some(2), Some(1), Some(1)]);
+        assert_eq!(domain.filter(&Predicate::Equals(DatValue::Int(1))), vec![0, 2, 3]);
+        assert!(Predicate::Equals(DatValue::ForeignRow(3)).matches(&DatValueRef::ForeignKey(3, 0)));
+        assert!(Predicate::Equals(DatValue::ForeignKey(3, 0)).matches_value(&DatValue::ForeignRow(3)));
+        assert!(!Predicate::Equals(DatValue::ForeignRow(3)).matches(&DatValueRef::Int(3)));
+
+        let rows = view.filter(&[("Domain", Predicate::Equals(DatValue::Int(1))), ("Level", Predicate::Range(15.0, 35.0))]).unwrap();
+        assert_eq!(rows, vec![2]);
+        assert!(view.filter(&[("Missing", Predicate::Range(0.0, 1.0))]).is_err());
+
+        let data = [&[0x41, 0, 0x62, 0, 0x3D, 0xD8, 0x00, 0xDE, 0x63, 0][..], &[0; 2]].concat(); // "Ab😀c"
+        let s = DatStr::at(&data, 0);
+        assert!(s.contains("b😀c") && s.contains("") && s.contains("Ab😀c"));
+        assert!(!s.contains("bc") && !s.contains("Ab😀cd"));
+        assert!(DatStr::at_with(&[0x41, 0, 0, 0, 0x62, 0, 0, 0], 0, StringEncoding::Utf32).contains("b"));
+    }
+}
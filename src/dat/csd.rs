@@ -1,13 +1,50 @@
 
 
 use serde::Serialize;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CsdFile {
     pub path: String,
     pub entries: Vec<CsdEntry>,
     pub languages: Vec<String>,
+    /// Paths from `include "..."` lines, in file order.
+    pub includes: Vec<String>,
+    pub encoding: CsdEncoding,
+    /// Problems skipped over while reading the file.
+    pub errors: Vec<CsdError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CsdEncoding {
+    Utf16Le,
+    Utf8,
+}
+
+/// A problem the parser recovered from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CsdError {
+    pub path: String,
+    pub position: CsdPosition,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CsdPosition {
+    /// Byte offset of undecodable input, which was replaced by U+FFFD.
+    Byte(usize),
+    /// 1-based line number of a malformed line, which was skipped.
+    Line(usize),
+}
+
+impl fmt::Display for CsdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            CsdPosition::Byte(offset) => write!(f, "{} (byte {}): {}", self.path, offset, self.message),
+            CsdPosition::Line(line) => write!(f, "{}:{}: {}", self.path, line, self.message),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -18,37 +55,100 @@ pub struct CsdEntry {
 
 #[derive(Debug, Clone, Serialize)]
 pub struct CsdSubEntry {
+    /// The raw condition text, e.g. `1|# #`.
     pub operator: String,
+    /// One condition per stat id of the entry.
+    pub conditions: Vec<CsdCondition>,
     pub description: String,
     pub is_canonical: bool,
-    pub parameters: Vec<CsdParameter>,
+    pub handlers: Vec<CsdHandler>,
     pub language: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct CsdParameter {
-    pub name: String,
-    pub value: i32,
+impl CsdSubEntry {
+    pub fn matches(&self, values: &[i32]) -> bool {
+        self.conditions.iter().zip(values).all(|(c, v)| c.matches(*v))
+    }
 }
 
-pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
-    // 1. Decode UTF-16LE
-    let u16_vec: Vec<u16> = data
-        .chunks_exact(2)
-        .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-        .collect();
+/// Value condition on one stat: `#`, `N`, `N|#`, `#|M`, `N|M` or `!N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CsdCondition {
+    /// Inclusive bounds, `None` for `#`.
+    Range { min: Option<i32>, max: Option<i32> },
+    NotEqual(i32),
+}
 
-    let content = String::from_utf16(&u16_vec)
-        .map_err(|e| format!("Failed to decode UTF-16LE: {}", e))?;
+impl CsdCondition {
+    pub const ANY: CsdCondition = CsdCondition::Range { min: None, max: None };
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let bound = |b: &str| match b {
+            "#" => Ok(None),
+            _ => b.parse::<i32>().map(Some).map_err(|_| format!("Invalid condition '{}'", s)),
+        };
+        if let Some(value) = s.strip_prefix('!') {
+            return value.parse().map(CsdCondition::NotEqual).map_err(|_| format!("Invalid condition '{}'", s));
+        }
+        match s.split_once('|') {
+            Some((min, max)) => Ok(CsdCondition::Range { min: bound(min)?, max: bound(max)? }),
+            None => {
+                let value = bound(s)?;
+                Ok(CsdCondition::Range { min: value, max: value })
+            }
+        }
+    }
+
+    pub fn matches(&self, value: i32) -> bool {
+        match *self {
+            CsdCondition::Range { min, max } => min.is_none_or(|m| value >= m) && max.is_none_or(|m| value <= m),
+            CsdCondition::NotEqual(v) => value != v,
+        }
+    }
+}
+
+/// Post-processing keyword following a description's text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum CsdHandler {
+    /// `<name> <stat>`, e.g. `negate 1`. The stat index is 1-based, as in the file.
+    Index { name: String, stat: usize },
+    /// `reminderstring <ReminderText id>`.
+    ReminderString(String),
+}
+
+/// Parses a stat description file, UTF-16LE or UTF-8 with or without a BOM. Undecodable input
+/// and malformed lines are skipped and listed in `errors`.
+pub fn parse_csd(data: &[u8], file_path: &str) -> CsdFile {
+    let (content, encoding, decode_errors) = decode(data);
+    let mut errors: Vec<CsdError> = decode_errors.into_iter()
+        .map(|(offset, message)| CsdError { path: file_path.to_string(), position: CsdPosition::Byte(offset), message })
+        .collect();
+    let mut syntax_error = |line: usize, message: String| {
+        errors.push(CsdError { path: file_path.to_string(), position: CsdPosition::Line(line), message });
+    };
 
     let mut entries = Vec::new();
     let mut languages = HashSet::new();
-    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut includes = Vec::new();
+    let lines: Vec<(usize, &str)> = content.lines()
+        .enumerate()
+        .map(|(n, l)| (n + 1, l))
+        .filter(|(_, l)| !l.trim().is_empty())
+        .collect();
 
     let mut i = 0;
     while i < lines.len() {
-        let line = lines[i];
-        if line.starts_with('\t') || line.starts_with("include") {
+        let (line_number, line) = lines[i];
+        if line.starts_with('\t') {
+            i += 1;
+            continue;
+        }
+
+        if line.starts_with("include") {
+            match line.split('"').nth(1) {
+                Some(path) => includes.push(path.to_string()),
+                None => syntax_error(line_number, "include without a quoted path".to_string()),
+            }
             i += 1;
             continue;
         }
@@ -60,6 +160,8 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
                     ids: vec![parts[1].to_string()],
                     descriptions: Vec::new(),
                 });
+            } else {
+                syntax_error(line_number, "no_description without a stat id".to_string());
             }
             i += 1;
             continue;
@@ -88,20 +190,25 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
             // So "description" is just a marker. The NEXT line contains content.
             i += 1;
             if i >= lines.len() { break; }
-            let id_line = lines[i];
+            let (id_line_number, id_line) = lines[i];
             let id_parts: Vec<&str> = id_line.split_whitespace().collect();
             
-            if let Some(count_str) = id_parts.get(0) {
-                 if let Ok(id_count) = count_str.parse::<usize>() {
-                     // Check range constraints from C# (count <= 0 or >= 5 continue??)
-                     // "if (partsCount is <= 0 or >= 5) continue;"
-                     if id_count > 0 && id_count < 10 { // Relaxed upper bound just in case
-                         for part in id_parts.iter().skip(1).take(id_count) {
-                             current_ids.push(part.to_string());
-                         }
-                     }
-                 }
-            }
+            // An entry without ids is still read past, but dropped: it could never be looked up
+            // and would overwrite other id-less entries when files are merged
+            let valid_ids = match id_parts.first().and_then(|c| c.parse::<usize>().ok()) {
+                // Check range constraints from C# (count <= 0 or >= 5 continue??)
+                // "if (partsCount is <= 0 or >= 5) continue;"
+                Some(id_count) if id_count > 0 && id_count < 10 && id_parts.len() > id_count => { // Relaxed upper bound just in case
+                    for part in id_parts.iter().skip(1).take(id_count) {
+                        current_ids.push(part.to_string());
+                    }
+                    true
+                }
+                _ => {
+                    syntax_error(id_line_number, format!("Invalid stat id line '{}'", id_line.trim()));
+                    false
+                }
+            };
 
             i += 1; // Move to count of descriptions
             if i >= lines.len() { break; }
@@ -115,13 +222,14 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
                 // Peek next line check?
                 if i + 1 >= lines.len() { break; }
                 
-                let next_line = lines[i+1].trim();
+                let next_line = lines[i+1].1.trim();
                 if next_line.starts_with("description") || next_line.starts_with("no_description") || next_line.starts_with("include") {
                     break;
                 }
                 
                 i += 1;
-                let line = lines[i].trim();
+                let (line_number, line) = lines[i];
+                let line = line.trim();
 
                 // Handle language switch
                 if line.starts_with("lang ") {
@@ -134,54 +242,22 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
                     continue; 
                 }
 
-                // Parse: Operator "Description" [Params...]
-                let parts: Vec<&str> = line.split('"').collect();
-                if parts.len() >= 2 {
-                    let operator = parts[0].trim().to_string();
-                    let description = parts[1].replace("\\n", "\n");
-                    
-                    let mut is_canonical = false;
-                    let mut parameters = Vec::new();
-
-                    if parts.len() > 2 {
-                        let param_str = parts[2..].join("\""); // Rejoin rest
-                        let param_parts: Vec<&str> = param_str.split_whitespace().collect();
-                        
-                        let mut p_idx = 0;
-                        while p_idx < param_parts.len() {
-                            if param_parts[p_idx] == "canonical_line" {
-                                is_canonical = true;
-                                p_idx += 1;
-                            } else if p_idx + 1 < param_parts.len() {
-                                let name = param_parts[p_idx].to_string();
-                                if let Ok(val) = param_parts[p_idx+1].parse::<i32>() {
-                                    parameters.push(CsdParameter { name, value: val });
-                                    p_idx += 2;
-                                } else {
-                                    p_idx += 1;
-                                }
-                            } else {
-                                p_idx += 1;
-                            }
+                match parse_sub_entry(line, current_lang.clone()) {
+                    Ok(Some(sub)) => {
+                        if current_lang.is_none() {
+                            has_base_desc = true;
                         }
+                        descriptions.push(sub);
                     }
-
-                    descriptions.push(CsdSubEntry {
-                        operator,
-                        description,
-                        is_canonical,
-                        parameters,
-                        language: current_lang.clone(),
-                    });
-                    
-                    if current_lang.is_none() {
-                        has_base_desc = true;
-                    }
+                    Ok(None) => {}
+                    Err(e) => syntax_error(line_number, e),
                 }
-
             }
 
-            
+            if !valid_ids {
+                i += 1;
+                continue;
+            }
             entries.push(CsdEntry {
                 ids: current_ids,
                 descriptions,
@@ -198,7 +274,7 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
         i += 1;
     }
 
-    Ok(CsdFile {
+    CsdFile {
         path: file_path.to_string(),
         entries,
         languages: {
@@ -206,7 +282,226 @@ pub fn parse_csd(data: &[u8], file_path: &str) -> Result<CsdFile, String> {
             l.sort();
             l
         }, // sorted list of languages
-    })
+        includes,
+        encoding,
+        errors,
+    }
+}
+
+/// Decodes by BOM, or by the zero high bytes of UTF-16LE ASCII without one. Returns the byte
+/// offset and description of every undecodable sequence.
+fn decode(data: &[u8]) -> (String, CsdEncoding, Vec<(usize, String)>) {
+    let mut errors = Vec::new();
+    if let Some(body) = data.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return (decode_utf8(body, 3, &mut errors), CsdEncoding::Utf8, errors);
+    }
+    let (body, start) = match data.strip_prefix(&[0xFF, 0xFE]) {
+        Some(body) => (body, 2),
+        None if data.iter().skip(1).step_by(2).take(256).any(|&b| b == 0) => (data, 0),
+        None => return (decode_utf8(data, 0, &mut errors), CsdEncoding::Utf8, errors),
+    };
+
+    let units = body.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+    let mut content = String::with_capacity(body.len() / 2);
+    let mut offset = start;
+    for c in char::decode_utf16(units) {
+        match c {
+            Ok(c) => {
+                content.push(c);
+                offset += c.len_utf16() * 2;
+            }
+            Err(e) => {
+                errors.push((offset, format!("Unpaired surrogate {:#06x}", e.unpaired_surrogate())));
+                content.push(char::REPLACEMENT_CHARACTER);
+                offset += 2;
+            }
+        }
+    }
+    if body.len() % 2 != 0 {
+        errors.push((start + body.len() - 1, "Trailing byte in UTF-16 data".to_string()));
+    }
+    (content, CsdEncoding::Utf16Le, errors)
+}
+
+fn decode_utf8(mut data: &[u8], mut offset: usize, errors: &mut Vec<(usize, String)>) -> String {
+    let mut content = String::with_capacity(data.len());
+    loop {
+        match std::str::from_utf8(data) {
+            Ok(text) => {
+                content.push_str(text);
+                return content;
+            }
+            Err(e) => {
+                let valid = e.valid_up_to();
+                content.push_str(std::str::from_utf8(&data[..valid]).unwrap_or_default());
+                content.push(char::REPLACEMENT_CHARACTER);
+                let skip = e.error_len().unwrap_or(data.len() - valid);
+                errors.push((offset + valid, format!("Invalid UTF-8 sequence of {} byte(s)", skip)));
+                data = &data[valid + skip..];
+                offset += valid + skip;
+            }
+        }
+    }
+}
+
+impl CsdHandler {
+    /// Stat index (0-based) and value transform of the handler, `None` for handlers that leave
+    /// values alone (and for `reminderstring`).
+    pub fn transform(&self) -> Option<(usize, ValueTransform)> {
+        match self {
+            CsdHandler::Index { name, stat } if *stat > 0 => ValueTransform::from_name(name).map(|t| (stat - 1, t)),
+            _ => None,
+        }
+    }
+}
+
+/// Linear value handler, e.g. `milliseconds_to_seconds_1dp` is a scale of 0.001 kept to 1 decimal.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ValueTransform {
+    pub scale: f64,
+    pub decimals: u32,
+}
+
+impl ValueTransform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.strip_suffix("_if_required").unwrap_or(name);
+        let (base, decimals) = match name.rsplit_once('_') {
+            Some((base, dp)) if dp.len() == 3 && dp.ends_with("dp") => match dp[..1].parse() {
+                Ok(decimals) => (base, Some(decimals)),
+                Err(_) => (name, None),
+            },
+            _ => (name, None),
+        };
+        let (scale, default_decimals) = match base {
+            "negate" => (-1.0, 0),
+            "double" => (2.0, 0),
+            "negate_and_double" => (-2.0, 0),
+            "times_twenty" => (20.0, 0),
+            "times_one_point_five" => (1.5, 1),
+            "multiplied_by_one_hundred" => (100.0, 0),
+            "divide_by_two" => (0.5, 1),
+            "divide_by_three" => (1.0 / 3.0, 1),
+            "divide_by_four" => (0.25, 2),
+            "divide_by_five" => (0.2, 1),
+            "divide_by_ten" => (0.1, 1),
+            "divide_by_twelve" => (1.0 / 12.0, 1),
+            "divide_by_fifteen" => (1.0 / 15.0, 1),
+            "divide_by_twenty" => (0.05, 2),
+            "divide_by_fifty" => (0.02, 2),
+            "divide_by_one_hundred" => (0.01, 2),
+            "divide_by_one_thousand" => (0.001, 3),
+            "per_minute_to_per_second" => (1.0 / 60.0, 1),
+            "milliseconds_to_seconds" => (0.001, 2),
+            "deciseconds_to_seconds" => (0.1, 1),
+            "locations_to_metres" => (0.1, 1),
+            _ => return None,
+        };
+        Some(Self { scale, decimals: decimals.unwrap_or(default_decimals) })
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        round(value * self.scale, self.decimals)
+    }
+
+    pub fn invert(&self, value: f64) -> f64 {
+        value / self.scale
+    }
+}
+
+fn round(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// Parses `Operator "Description" [handlers...]`, `None` for lines without a description.
+fn parse_sub_entry(line: &str, language: Option<String>) -> Result<Option<CsdSubEntry>, String> {
+    let parts: Vec<&str> = line.split('"').collect();
+    if parts.len() < 2 {
+        return Ok(None);
+    }
+    let operator = parts[0].trim().to_string();
+    let conditions = operator.split_whitespace().map(CsdCondition::parse).collect::<Result<Vec<_>, _>>()?;
+    let description = parts[1].replace("\\n", "\n");
+
+    let mut is_canonical = false;
+    let mut handlers = Vec::new();
+    let param_str = parts[2..].join("\""); // Rejoin rest
+    let mut tokens = param_str.split_whitespace().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            "canonical_line" => is_canonical = true,
+            "reminderstring" => {
+                if let Some(id) = tokens.next() {
+                    handlers.push(CsdHandler::ReminderString(id.to_string()));
+                }
+            }
+            name => {
+                // Handlers take a stat index; anything else is a keyword we do not know
+                if let Some(stat) = tokens.peek().and_then(|t| t.parse::<usize>().ok()) {
+                    tokens.next();
+                    handlers.push(CsdHandler::Index { name: name.to_string(), stat });
+                }
+            }
+        }
+    }
+
+    Ok(Some(CsdSubEntry { operator, conditions, description, is_canonical, handlers, language }))
+}
+
+/// Parses `path` and, recursively, the files it includes, all read through `read`. Included
+/// entries come first; an entry for the same stat ids further down replaces them in place.
+/// `errors` collects those of every file.
+pub fn load_csd<F>(path: &str, mut read: F) -> Result<CsdFile, String>
+where
+    F: FnMut(&str) -> Result<Vec<u8>, String>,
+{
+    let mut files = Vec::new();
+    collect_includes(path, &mut read, &mut Vec::new(), &mut files)?;
+
+    let mut entries: Vec<CsdEntry> = Vec::new();
+    let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut languages: HashSet<String> = HashSet::new();
+    let mut errors = Vec::new();
+    for file in &mut files {
+        languages.extend(file.languages.drain(..));
+        errors.append(&mut file.errors);
+        for entry in file.entries.drain(..) {
+            match positions.get(&entry.ids) {
+                Some(&i) => entries[i] = entry,
+                None => {
+                    positions.insert(entry.ids.clone(), entries.len());
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    let mut root = files.pop().ok_or_else(|| format!("Failed to load {}", path))?;
+    root.entries = entries;
+    root.errors = errors;
+    root.languages = languages.into_iter().collect();
+    root.languages.sort();
+    Ok(root)
+}
+
+/// Depth-first, includes before the including file; each file is loaded once.
+fn collect_includes<F>(path: &str, read: &mut F, stack: &mut Vec<String>, files: &mut Vec<CsdFile>) -> Result<(), String>
+where
+    F: FnMut(&str) -> Result<Vec<u8>, String>,
+{
+    if stack.iter().any(|p| p.eq_ignore_ascii_case(path)) {
+        return Err(format!("Include cycle: {} -> {}", stack.join(" -> "), path));
+    }
+    if files.iter().any(|f| f.path.eq_ignore_ascii_case(path)) {
+        return Ok(());
+    }
+    let file = parse_csd(&read(path)?, path);
+    stack.push(path.to_string());
+    for include in &file.includes {
+        collect_includes(include, read, stack, files)?;
+    }
+    stack.pop();
+    files.push(file);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -231,7 +526,7 @@ lang \"Japanese\"
             bytes.extend_from_slice(&u.to_le_bytes());
         }
 
-        let result = parse_csd(&bytes, "test.csd").expect("Failed to parse");
+        let result = parse_csd(&bytes, "test.csd");
         assert_eq!(result.entries.len(), 1);
         let entry = &result.entries[0];
         assert_eq!(entry.ids.len(), 2);
@@ -253,4 +548,86 @@ lang \"Japanese\"
         assert!(result.languages.contains(&"Japanese".to_string()));
         assert!(result.languages.contains(&"English".to_string()));
     }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_csd_conditions_and_includes() {
+        let base = "description
+\t1 base_cold_damage_resistance_%
+\t2
+\t\t1|# \"{0:+d}% to Cold Resistance\"
+\t\t#|-1 \"{0}% reduced Cold Resistance\" negate 1 reminderstring ReminderTextColdRes
+description
+\t1 attack_speed_+%
+\t1
+\t\t# \"{0}% Attack Speed\"";
+        let child = "include \"Metadata/StatDescriptions/stat_descriptions.txt\"
+description
+\t2 attack_speed_+% local_flag
+\t1
+\t\t!0 0 \"{0}% increased Attack Speed\" per_minute_to_per_second 1 canonical_line
+description
+\t1 attack_speed_+%
+\t1
+\t\t# \"{0}% increased Attack Speed\"";
+        let read = |path: &str| match path {
+            "Metadata/StatDescriptions/stat_descriptions.txt" => Ok(utf16(base)),
+            "Metadata/StatDescriptions/skill_stat_descriptions.txt" => Ok(utf16(child)),
+            _ => Err(format!("{} not found", path)),
+        };
+
+        let file = load_csd("Metadata/StatDescriptions/skill_stat_descriptions.txt", read).unwrap();
+        assert_eq!(file.includes, vec!["Metadata/StatDescriptions/stat_descriptions.txt"]);
+        assert_eq!(file.entries.len(), 3);
+
+        let resistance = &file.entries[0].descriptions;
+        assert_eq!(resistance[0].conditions, vec![CsdCondition::Range { min: Some(1), max: None }]);
+        assert_eq!(resistance[1].conditions, vec![CsdCondition::Range { min: None, max: Some(-1) }]);
+        assert_eq!(resistance[1].handlers, vec![
+            CsdHandler::Index { name: "negate".to_string(), stat: 1 },
+            CsdHandler::ReminderString("ReminderTextColdRes".to_string()),
+        ]);
+        assert!(resistance[0].matches(&[25]) && !resistance[0].matches(&[-5]));
+
+        // Overridden in place by the including file
+        assert_eq!(file.entries[1].ids, vec!["attack_speed_+%"]);
+        assert_eq!(file.entries[1].descriptions[0].description, "{0}% increased Attack Speed");
+
+        let combined = &file.entries[2].descriptions[0];
+        assert_eq!(combined.conditions, vec![CsdCondition::NotEqual(0), CsdCondition::Range { min: Some(0), max: Some(0) }]);
+        assert!(combined.is_canonical);
+        assert!(combined.matches(&[5, 0]) && !combined.matches(&[0, 0]));
+    }
+
+    #[test]
+    fn test_csd_encodings_and_errors() {
+        let text = "description\n\t1 life\n\t1\n\t\t# \"{0} to maximum Life\"\ndescription\n\tlife\n\t1\n\t\tx|# \"Broken\"\n";
+
+        let mut utf8 = vec![0xEF, 0xBB, 0xBF];
+        utf8.extend_from_slice(text.as_bytes());
+        let file = parse_csd(&utf8, "utf8.csd");
+        assert_eq!(file.encoding, CsdEncoding::Utf8);
+        assert_eq!(file.entries[0].descriptions[0].description, "{0} to maximum Life");
+        assert_eq!(file.errors.iter().map(|e| e.position).collect::<Vec<_>>(), vec![CsdPosition::Line(6), CsdPosition::Line(8)]);
+        assert_eq!(file.errors[1].to_string(), "utf8.csd:8: Invalid condition 'x|#'");
+        assert_eq!(file.entries.len(), 1);
+
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(utf16(&text[..text.find("description\n\tlife").unwrap()]));
+        with_bom.extend_from_slice(&0xD800u16.to_le_bytes());
+        with_bom.push(b'x');
+        let file = parse_csd(&with_bom, "utf16.csd");
+        assert_eq!(file.encoding, CsdEncoding::Utf16Le);
+        assert_eq!(file.entries.len(), 1);
+        assert_eq!(file.errors.len(), 2);
+        assert_eq!(file.errors[0].position, CsdPosition::Byte(with_bom.len() - 3));
+        assert_eq!(file.errors[1].position, CsdPosition::Byte(with_bom.len() - 1));
+
+        let file = parse_csd(&utf16(text), "plain.csd");
+        assert_eq!(file.encoding, CsdEncoding::Utf16Le);
+        assert_eq!(parse_csd(b"no_description life\n\xFFinclude", "bad.csd").errors[0].position, CsdPosition::Byte(20));
+    }
 }
@@ -0,0 +1,172 @@
+use serde::Serialize;
+use super::csd::{CsdCondition, CsdFile, CsdSubEntry};
+use super::csd_render::{segments, Segment};
+
+/// Stats a line of display text may have been rendered from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatMatch<'a> {
+    /// Index into `CsdFile::entries`.
+    pub entry: usize,
+    /// Every stat of the entry with a known value: read from the text, or fixed by the
+    /// description's conditions (e.g. the `0` of `# 0`).
+    pub stats: Vec<(&'a str, i32)>,
+}
+
+enum Part {
+    Literal(String),
+    Value { stat: usize },
+}
+
+struct Template<'a> {
+    entry: usize,
+    sub: &'a CsdSubEntry,
+    parts: Vec<Part>,
+}
+
+/// Parses item text back into stats, with the descriptions of a `CsdFile` in one language.
+pub struct StatMatcher<'a> {
+    file: &'a CsdFile,
+    templates: Vec<Template<'a>>,
+}
+
+impl<'a> StatMatcher<'a> {
+    /// Compiles the descriptions of `language` (`None` for English).
+    pub fn new(file: &'a CsdFile, language: Option<&str>) -> Self {
+        let mut templates = Vec::new();
+        for (entry, e) in file.entries.iter().enumerate() {
+            for sub in e.descriptions.iter().filter(|d| d.language.as_deref() == language) {
+                let parts = segments(&sub.description).into_iter()
+                    .map(|segment| match segment {
+                        Segment::Value { stat, .. } if stat < e.ids.len() => Part::Value { stat },
+                        Segment::Value { raw, .. } => Part::Literal(raw.to_string()),
+                        Segment::Text(text) => Part::Literal(text.to_string()),
+                    })
+                    .collect();
+                templates.push(Template { entry, sub, parts });
+            }
+        }
+        Self { file, templates }
+    }
+
+    /// Candidate stats for `line`, in file order. Handlers are undone (e.g. `negate`, per-minute
+    /// values shown per second) and candidates whose values break the description's conditions
+    /// are dropped, so "8% reduced" yields -8 only through the description written for negatives.
+    pub fn match_line(&self, line: &str) -> Vec<StatMatch<'a>> {
+        let line = line.trim();
+        let mut matches: Vec<StatMatch<'a>> = Vec::new();
+        for template in &self.templates {
+            let Some(values) = self.match_template(template, line) else { continue };
+            let candidate = StatMatch { entry: template.entry, stats: values };
+            if !matches.contains(&candidate) {
+                matches.push(candidate);
+            }
+        }
+        matches
+    }
+
+    fn match_template(&self, template: &Template<'a>, line: &str) -> Option<Vec<(&'a str, i32)>> {
+        let ids = &self.file.entries[template.entry].ids;
+        let mut read: Vec<Option<f64>> = vec![None; ids.len()];
+        let mut rest = line;
+        for part in &template.parts {
+            match part {
+                Part::Literal(text) => rest = rest.strip_prefix(text.as_str())?,
+                Part::Value { stat } => {
+                    let (value, len) = parse_number(rest)?;
+                    match read[*stat] {
+                        Some(previous) if previous != value => return None,
+                        _ => read[*stat] = Some(value),
+                    }
+                    rest = &rest[len..];
+                }
+            }
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+
+        for (stat, transform) in template.sub.handlers.iter().filter_map(|h| h.transform()) {
+            if let Some(Some(value)) = read.get_mut(stat) {
+                *value = transform.invert(*value);
+            }
+        }
+
+        let mut stats = Vec::new();
+        for (i, id) in ids.iter().enumerate() {
+            let condition = template.sub.conditions.get(i).copied().unwrap_or(CsdCondition::ANY);
+            let value = match (read[i], condition) {
+                (Some(value), _) => value.round() as i32,
+                (None, CsdCondition::Range { min: Some(min), max: Some(max) }) if min == max => min,
+                (None, _) => continue,
+            };
+            if !condition.matches(value) {
+                return None;
+            }
+            stats.push((id.as_str(), value));
+        }
+        Some(stats)
+    }
+}
+
+/// `[+-]digits[.digits]` at the start of `text`, with its length.
+fn parse_number(text: &str) -> Option<(f64, usize)> {
+    let bytes = text.as_bytes();
+    let mut len = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+    let integer = digits(len);
+    if integer == 0 {
+        return None;
+    }
+    len += integer;
+    if bytes.get(len) == Some(&b'.') {
+        let fraction = digits(len + 1);
+        if fraction > 0 {
+            len += 1 + fraction;
+        }
+    }
+    text[..len].parse().ok().map(|value| (value, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::csd::parse_csd;
+
+    #[test]
+    fn test_match_line() {
+        let text = "description
+\t1 attack_speed_+%
+\t2
+\t\t1|# \"{0}% increased Attack Speed\"
+\t\t#|-1 \"{0}% reduced Attack Speed\" negate 1
+description
+\t2 attack_minimum_added_fire_damage attack_maximum_added_fire_damage
+\t1
+\t\t# # \"Adds {0} to {1} Fire Damage to Attacks\"
+description
+\t2 base_life_regeneration_rate_per_minute local_flag
+\t1
+\t\t# 1 \"Regenerate {0:+d} Life per second\" per_minute_to_per_second 1
+description
+\t1 base_fire_damage_resistance_%
+\t1
+\t\t# \"+{0}% to [Resistances|Fire Resistance]\"";
+        let bytes: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let file = parse_csd(&bytes, "stat_descriptions.txt");
+        let matcher = StatMatcher::new(&file, None);
+
+        assert_eq!(matcher.match_line("8% reduced Attack Speed"), vec![StatMatch { entry: 0, stats: vec![("attack_speed_+%", -8)] }]);
+        assert_eq!(matcher.match_line("12% increased Attack Speed")[0].stats, vec![("attack_speed_+%", 12)]);
+        assert_eq!(matcher.match_line("Adds 3 to 12 Fire Damage to Attacks")[0].stats, vec![
+            ("attack_minimum_added_fire_damage", 3),
+            ("attack_maximum_added_fire_damage", 12),
+        ]);
+        assert_eq!(matcher.match_line(" Regenerate +1.5 Life per second")[0].stats, vec![
+            ("base_life_regeneration_rate_per_minute", 90),
+            ("local_flag", 1),
+        ]);
+        assert_eq!(matcher.match_line("+30% to Fire Resistance")[0].stats, vec![("base_fire_damage_resistance_%", 30)]);
+        assert!(matcher.match_line("-8% reduced Attack Speed").is_empty());
+        assert!(matcher.match_line("12% increased Cast Speed").is_empty());
+    }
+}
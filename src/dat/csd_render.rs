@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use super::csd::{CsdEntry, CsdFile, CsdSubEntry};
+
+/// A stat value, or the roll range of a mod's stat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatValue {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl StatValue {
+    pub fn range(min: i32, max: i32) -> Self {
+        Self { min, max }
+    }
+}
+
+impl From<i32> for StatValue {
+    fn from(value: i32) -> Self {
+        Self { min: value, max: value }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RenderedStats {
+    /// One string per matched description, which may span several lines.
+    pub lines: Vec<String>,
+    /// Stats with no description entry at all. Stats hidden on purpose (`no_description`, or
+    /// values no condition matches) are not listed.
+    pub missing: Vec<String>,
+}
+
+/// Turns stat ids and values into display text with the descriptions of a `CsdFile`.
+pub struct StatRenderer<'a> {
+    file: &'a CsdFile,
+    by_stat: HashMap<&'a str, Vec<usize>>, // stat id -> entries, in file order
+}
+
+impl<'a> StatRenderer<'a> {
+    pub fn new(file: &'a CsdFile) -> Self {
+        let mut by_stat: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, entry) in file.entries.iter().enumerate() {
+            for id in &entry.ids {
+                by_stat.entry(id.as_str()).or_default().push(index);
+            }
+        }
+        Self { file, by_stat }
+    }
+
+    /// Renders `stats` in `language` (`None` for English), falling back to English for entries
+    /// without a translation. Multi-stat entries take all their stats at once; stats they need but
+    /// that are not given count as 0.
+    pub fn render<S: AsRef<str>>(&self, stats: &[(S, StatValue)], language: Option<&str>) -> RenderedStats {
+        let values: HashMap<&str, StatValue> = stats.iter().map(|(id, v)| (id.as_ref(), *v)).collect();
+        let mut done: HashSet<&str> = HashSet::new();
+        let mut rendered = RenderedStats::default();
+
+        for (id, _) in stats {
+            let id = id.as_ref();
+            if done.contains(id) {
+                continue;
+            }
+            let Some(entry) = self.entry_for(id, &values) else {
+                rendered.missing.push(id.to_string());
+                done.insert(id);
+                continue;
+            };
+            let entry_values: Vec<StatValue> = entry.ids.iter()
+                .map(|i| values.get(i.as_str()).copied().unwrap_or(StatValue::from(0)))
+                .collect();
+            done.extend(entry.ids.iter().filter_map(|i| values.get_key_value(i.as_str()).map(|(k, _)| *k)));
+
+            if let Some(sub) = select(entry, &entry_values, language) {
+                rendered.lines.push(format_description(sub, &entry_values));
+            }
+        }
+        rendered
+    }
+
+    /// The entry covering most of the given stats, the first in the file on ties.
+    fn entry_for(&self, id: &str, values: &HashMap<&str, StatValue>) -> Option<&'a CsdEntry> {
+        let mut best: Option<(&CsdEntry, usize)> = None;
+        for &index in self.by_stat.get(id)? {
+            let entry = &self.file.entries[index];
+            let present = entry.ids.iter().filter(|i| values.contains_key(i.as_str())).count();
+            if best.is_none_or(|(_, n)| present > n) {
+                best = Some((entry, present));
+            }
+        }
+        best.map(|(entry, _)| entry)
+    }
+}
+
+/// First description of `language` whose conditions hold for the roll range (or failing that,
+/// its upper end); English when the language has no descriptions for the entry.
+fn select<'e>(entry: &'e CsdEntry, values: &[StatValue], language: Option<&str>) -> Option<&'e CsdSubEntry> {
+    let lang = if entry.descriptions.iter().any(|d| d.language.as_deref() == language) { language } else { None };
+    let candidates: Vec<&CsdSubEntry> = entry.descriptions.iter().filter(|d| d.language.as_deref() == lang).collect();
+    let mins: Vec<i32> = values.iter().map(|v| v.min).collect();
+    let maxs: Vec<i32> = values.iter().map(|v| v.max).collect();
+    candidates.iter().find(|d| d.matches(&mins) && d.matches(&maxs))
+        .or_else(|| candidates.iter().find(|d| d.matches(&maxs)))
+        .copied()
+}
+
+/// Substitutes `{0}`, `{0:+d}`, `{}` and `{:+d}` placeholders, after the description's handlers.
+pub fn format_description(sub: &CsdSubEntry, values: &[StatValue]) -> String {
+    let mut ranges: Vec<(f64, f64)> = values.iter().map(|v| (v.min as f64, v.max as f64)).collect();
+    for (stat, transform) in sub.handlers.iter().filter_map(|h| h.transform()) {
+        if let Some((min, max)) = ranges.get_mut(stat) {
+            let (a, b) = (transform.apply(*min), transform.apply(*max));
+            (*min, *max) = if a <= b { (a, b) } else { (b, a) };
+        }
+    }
+
+    let mut out = String::with_capacity(sub.description.len());
+    for segment in segments(&sub.description) {
+        match segment {
+            Segment::Value { stat, signed, raw } => match ranges.get(stat) {
+                Some(&(min, max)) => out.push_str(&format_range(min, max, signed)),
+                None => out.push_str(raw),
+            },
+            Segment::Text(text) => out.push_str(text),
+        }
+    }
+    out
+}
+
+pub(crate) enum Segment<'t> {
+    Text(&'t str),
+    /// A placeholder; `raw` is its text including the braces.
+    Value { stat: usize, signed: bool, raw: &'t str },
+}
+
+/// Splits a description into text and `{0}`, `{0:+d}`, `{}`, `{:+d}` placeholders. Markup links
+/// (`[Key|Text]`, or `[Key]` alone) are replaced by their display text.
+pub(crate) fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut next = 0;
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else { break };
+        push_text(&mut segments, &rest[..start]);
+        let raw = &rest[start..=start + len];
+        let inner = &raw[1..raw.len() - 1];
+        let (index, spec) = inner.split_once(':').unwrap_or((inner, ""));
+        let index = if index.is_empty() { Some(next) } else { index.parse::<usize>().ok() };
+        match index {
+            Some(stat) => {
+                segments.push(Segment::Value { stat, signed: spec.starts_with('+'), raw });
+                next = stat + 1;
+            }
+            None => segments.push(Segment::Text(raw)),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    push_text(&mut segments, rest);
+    segments
+}
+
+fn push_text<'t>(segments: &mut Vec<Segment<'t>>, mut text: &'t str) {
+    while let Some(start) = text.find('[') {
+        let Some(len) = text[start..].find(']') else { break };
+        let link = &text[start + 1..start + len];
+        let display = link.split_once('|').map_or(link, |(_, display)| display);
+        segments.extend([&text[..start], display].into_iter().filter(|t| !t.is_empty()).map(Segment::Text));
+        text = &text[start + len + 1..];
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+}
+
+/// `5`, `+5`, `(10-20)` or `+(10-20)`.
+fn format_range(min: f64, max: f64, signed: bool) -> String {
+    let sign = if signed && min >= 0.0 { "+" } else { "" };
+    if min == max {
+        format!("{}{}", sign, format_number(min))
+    } else {
+        format!("{}({}-{})", sign, format_number(min), format_number(max))
+    }
+}
+
+fn format_number(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::csd::parse_csd;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_render_stats() {
+        let text = "description
+\t1 attack_speed_+%
+\t2
+\t\t1|# \"{0}% increased Attack Speed\"
+\t\t#|-1 \"{0}% reduced Attack Speed\" negate 1
+lang \"French\"
+\t1
+\t\t# \"{0:+d}% de vitesse d'attaque\"
+description
+\t2 attack_minimum_added_fire_damage attack_maximum_added_fire_damage
+\t1
+\t\t# # \"Adds {0} to {1} Fire Damage to Attacks\"
+description
+\t1 base_life_regeneration_rate_per_minute
+\t1
+\t\t# \"Regenerate {0:+d} Life per second\" per_minute_to_per_second 1
+description
+\t1 base_strength
+\t1
+\t\t# \"{0:+d} to [Strength]\"
+no_description dummy_stat";
+        let file = parse_csd(&utf16(text), "stat_descriptions.txt");
+        let renderer = StatRenderer::new(&file);
+
+        let rendered = renderer.render(&[
+            ("attack_speed_+%", StatValue::range(10, 20)),
+            ("attack_maximum_added_fire_damage", StatValue::from(12)),
+            ("attack_minimum_added_fire_damage", StatValue::from(3)),
+            ("base_life_regeneration_rate_per_minute", StatValue::from(90)),
+            ("dummy_stat", StatValue::from(1)),
+            ("unknown_stat", StatValue::from(1)),
+        ], None);
+        assert_eq!(rendered.lines, vec![
+            "(10-20)% increased Attack Speed",
+            "Adds 3 to 12 Fire Damage to Attacks",
+            "Regenerate +1.5 Life per second",
+        ]);
+        assert_eq!(rendered.missing, vec!["unknown_stat"]);
+        assert_eq!(renderer.render(&[("base_strength", StatValue::from(10))], None).lines, vec!["+10 to Strength"]);
+
+        assert_eq!(renderer.render(&[("attack_speed_+%", StatValue::from(-8))], None).lines, vec!["8% reduced Attack Speed"]);
+        assert_eq!(renderer.render(&[("attack_speed_+%", StatValue::range(10, 20))], Some("French")).lines, vec!["+(10-20)% de vitesse d'attaque"]);
+        assert_eq!(renderer.render(&[("base_life_regeneration_rate_per_minute", StatValue::from(60))], Some("French")).lines, vec!["Regenerate +1 Life per second"]);
+    }
+}
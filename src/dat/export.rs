@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use serde_json::{json, Value};
+use super::reader::{DatReader, DatValue};
+use super::schema::{Column, Table};
+use super::types::ColumnType;
+
+/// Display text for the rows of referenced tables, used to replace foreign keys on export.
+#[derive(Debug, Default, Clone)]
+pub struct ReferenceLabels {
+    tables: HashMap<String, Vec<String>>,
+}
+
+impl ReferenceLabels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Labels `table` by its display column: `Id` if present, otherwise the first string column.
+    pub fn add_table(&mut self, reader: &DatReader<'_>, table: &Table) -> io::Result<()> {
+        let column = display_column(table)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Table {} has no string column to display", table.name)))?;
+        self.add_table_column(reader, table, column)
+    }
+
+    pub fn add_table_column(&mut self, reader: &DatReader<'_>, table: &Table, column: &str) -> io::Result<()> {
+        let view = reader.view(table)?;
+        let column = view.column_by_name(column)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} not in table {}", column, table.name)))?;
+        let labels = column.to_vec().iter().map(csv_field).collect();
+        self.tables.insert(table.name.clone(), labels);
+        Ok(())
+    }
+
+    pub fn get(&self, table: &str, row: usize) -> Option<&str> {
+        self.tables.get(table)?.get(row).map(String::as_str)
+    }
+}
+
+fn display_column(table: &Table) -> Option<&str> {
+    let strings = || table.columns.iter().filter(|c| !c.array && matches!(c.r#type.parse(), Ok(ColumnType::String)));
+    strings().find(|c| c.name.as_deref() == Some("Id"))
+        .or_else(|| strings().next())
+        .and_then(|c| c.name.as_deref())
+}
+
+/// Writes the rows of a table as CSV or JSON Lines, and as Arrow IPC with the `arrow` feature.
+/// Array columns are read out in full; unnamed columns are called `Unknown<index>`.
+pub struct TableExporter<'a> {
+    reader: &'a DatReader<'a>,
+    table: &'a Table,
+    labels: Option<&'a ReferenceLabels>,
+}
+
+impl<'a> TableExporter<'a> {
+    pub fn new(reader: &'a DatReader<'a>, table: &'a Table) -> Self {
+        Self { reader, table, labels: None }
+    }
+
+    /// Replace foreign keys into any table known to `labels` with that row's display text.
+    pub fn with_labels(mut self, labels: &'a ReferenceLabels) -> Self {
+        self.labels = Some(labels);
+        self
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.table.columns.iter().enumerate()
+            .map(|(i, c)| c.display_name(i))
+            .collect()
+    }
+
+    /// Every row with arrays expanded and references resolved.
+    pub fn rows(&self) -> impl Iterator<Item = io::Result<Vec<DatValue>>> + '_ {
+        (0..self.reader.row_count).map(move |i| {
+            let mut values = self.reader.read_row_expanded(i, self.table)?;
+            for (value, col) in values.iter_mut().zip(&self.table.columns) {
+                self.resolve(value, col);
+            }
+            Ok(values)
+        })
+    }
+
+    fn resolve(&self, value: &mut DatValue, col: &Column) {
+        let Some(labels) = self.labels else { return };
+        let target = match &col.references {
+            Some(r) => r.table.as_str(),
+            None if matches!(col.r#type.parse(), Ok(ColumnType::Row)) => self.table.name.as_str(),
+            None => return,
+        };
+        match value {
+            DatValue::ForeignRow(row) | DatValue::ForeignKey(row, _) => {
+                if let Some(label) = labels.get(target, *row) {
+                    *value = DatValue::String(label.to_string());
+                }
+            },
+            DatValue::Array(items) => items.iter_mut().for_each(|item| self.resolve(item, col)),
+            _ => {},
+        }
+    }
+
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_csv_record(&mut writer, self.headers().iter().map(String::as_str))?;
+        for row in self.rows() {
+            let fields: Vec<String> = row?.iter().map(csv_field).collect();
+            write_csv_record(&mut writer, fields.iter().map(String::as_str))?;
+        }
+        Ok(())
+    }
+
+    /// One JSON object per row, keyed by column name, values in `DatValue`'s serde form.
+    pub fn write_jsonl<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let headers = self.headers();
+        for row in self.rows() {
+            // Written by hand to keep the schema's column order
+            writer.write_all(b"{")?;
+            for (i, (name, value)) in headers.iter().zip(row?).enumerate() {
+                if i > 0 { writer.write_all(b",")?; }
+                serde_json::to_writer(&mut writer, name)?;
+                writer.write_all(b":")?;
+                serde_json::to_writer(&mut writer, &value)?;
+            }
+            writer.write_all(b"}\n")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_csv_record<'s, W: Write>(writer: &mut W, fields: impl Iterator<Item = &'s str>) -> io::Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 { writer.write_all(b",")?; }
+        if field.contains([',', '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
+
+/// Text of a value in a CSV cell: nulls are empty, arrays and intervals JSON arrays.
+fn csv_field(value: &DatValue) -> String {
+    match value {
+        DatValue::String(s) => s.clone(),
+        DatValue::Null | DatValue::Unknown => String::new(),
+        other => match plain_json(other) {
+            Value::String(s) => s,
+            v => v.to_string(),
+        },
+    }
+}
+
+/// `value` as untagged JSON, e.g. `[1,2]` rather than `{"Array":[{"Int":1},{"Int":2}]}`.
+fn plain_json(value: &DatValue) -> Value {
+    match value {
+        DatValue::Bool(b) => json!(b),
+        DatValue::Int(i) => json!(i),
+        DatValue::Long(l) => json!(l),
+        DatValue::Float(f) => json!(f),
+        DatValue::String(s) => json!(s),
+        DatValue::ForeignRow(r) | DatValue::ForeignKey(r, _) | DatValue::EnumRow(r) => json!(r),
+        DatValue::Interval(min, max) => json!([min, max]),
+        DatValue::List(count, offset) => json!({ "count": count, "offset": offset }),
+        DatValue::Array(items) => Value::Array(items.iter().map(plain_json).collect()),
+        DatValue::Null | DatValue::Unknown => Value::Null,
+    }
+}
+
+#[cfg(feature = "arrow")]
+mod arrow {
+    use std::io::{self, Write};
+    use std::sync::Arc;
+    use arrow_array::{ArrayRef, BooleanArray, Float32Array, Int64Array, RecordBatch, StringArray, UInt64Array};
+    use arrow_ipc::writer::FileWriter;
+    use arrow_schema::{ArrowError, DataType, Field, Schema};
+    use super::{csv_field, plain_json, DatValue, TableExporter};
+    use crate::dat::types::{ColumnSpec, ColumnType};
+
+    fn to_io(e: ArrowError) -> io::Error {
+        io::Error::other(e)
+    }
+
+    impl TableExporter<'_> {
+        /// The whole table as one record batch. Arrays and intervals are JSON-encoded strings,
+        /// resolved references strings, other references row indices.
+        pub fn to_record_batch(&self) -> io::Result<RecordBatch> {
+            let rows = self.rows().collect::<io::Result<Vec<_>>>()?;
+            let mut fields = Vec::new();
+            let mut columns: Vec<ArrayRef> = Vec::new();
+            for (index, (name, col)) in self.headers().into_iter().zip(&self.table.columns).enumerate() {
+                let spec = ColumnSpec::from_column(col)?;
+                let values = rows.iter().map(|row| &row[index]);
+                let resolved = rows.iter().any(|row| matches!(row[index], DatValue::String(_)));
+                let (data_type, array): (DataType, ArrayRef) = match spec.ty {
+                    _ if spec.array || spec.interval || resolved => (DataType::Utf8, Arc::new(
+                        values.map(|v| match v {
+                            DatValue::Null | DatValue::Unknown => None,
+                            DatValue::String(s) => Some(s.clone()),
+                            other => Some(plain_json(other).to_string()),
+                        }).collect::<StringArray>())),
+                    ColumnType::Bool => (DataType::Boolean, Arc::new(
+                        values.map(|v| match v { DatValue::Bool(b) => Some(*b), _ => None }).collect::<BooleanArray>())),
+                    ColumnType::F32 => (DataType::Float32, Arc::new(
+                        values.map(|v| match v { DatValue::Float(f) => Some(*f), _ => None }).collect::<Float32Array>())),
+                    ColumnType::Row | ColumnType::ForeignRow | ColumnType::EnumRow | ColumnType::Rid | ColumnType::U64 => (DataType::UInt64, Arc::new(
+                        values.map(|v| match v {
+                            DatValue::ForeignRow(r) | DatValue::ForeignKey(r, _) | DatValue::EnumRow(r) => Some(*r as u64),
+                            DatValue::Long(l) => Some(*l),
+                            _ => None,
+                        }).collect::<UInt64Array>())),
+                    ColumnType::String | ColumnType::Unknown => (DataType::Utf8, Arc::new(
+                        values.map(|v| Some(csv_field(v))).collect::<StringArray>())),
+                    _ => (DataType::Int64, Arc::new(
+                        values.map(|v| match v {
+                            DatValue::Int(i) => Some(*i),
+                            DatValue::Long(l) => Some(*l as i64),
+                            _ => None,
+                        }).collect::<Int64Array>())),
+                };
+                fields.push(Field::new(name, data_type, true));
+                columns.push(array);
+            }
+            RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).map_err(to_io)
+        }
+
+        /// Writes the table as an Arrow IPC file, readable by pandas, polars and DuckDB.
+        pub fn write_arrow<W: Write>(&self, writer: W) -> io::Result<()> {
+            let batch = self.to_record_batch()?;
+            let mut writer = FileWriter::try_new(writer, &batch.schema()).map_err(to_io)?;
+            writer.write(&batch).map_err(to_io)?;
+            writer.finish().map_err(to_io)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{column, reference, table};
+
+    fn utf16(s: &str) -> Vec<u8> {
+        s.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_export() {
+        // Tags: one row with Id "a,b"
+        let mut tags = Vec::new();
+        tags.extend_from_slice(&1u32.to_le_bytes());
+        tags.extend_from_slice(&8u64.to_le_bytes());
+        tags.extend_from_slice(&[0xBB; 8]);
+        tags.extend_from_slice(&utf16("a,b"));
+        let tags_table = table("Tags", vec![column("Id", "string")]);
+        let tags_reader = DatReader::new(tags, "Tags.datc64").unwrap();
+
+        // Mods: one row of (Id: string, Values: [i32], Tag: foreignrow -> Tags)
+        let mut data = Vec::new();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&8u64.to_le_bytes());
+        data.extend_from_slice(&2u64.to_le_bytes());
+        data.extend_from_slice(&(8u64 + 10).to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&[0xBB; 8]);
+        data.extend_from_slice(&utf16("Str\""));
+        data.extend_from_slice(&3i32.to_le_bytes());
+        data.extend_from_slice(&4i32.to_le_bytes());
+
+        let table = table("Mods", vec![
+            column("Id", "string"),
+            Column { array: true, ..column("Values", "i32") },
+            Column { references: reference("Tags"), ..column("Tag", "foreignrow") },
+        ]);
+        let reader = DatReader::new(data, "Mods.datc64").unwrap();
+        let exporter = TableExporter::new(&reader, &table);
+
+        let mut csv = Vec::new();
+        exporter.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap(), "Id,Values,Tag\r\n\"Str\"\"\",\"[3,4]\",0\r\n");
+
+        let mut labels = ReferenceLabels::new();
+        labels.add_table(&tags_reader, &tags_table).unwrap();
+        let mut jsonl = Vec::new();
+        TableExporter::new(&reader, &table).with_labels(&labels).write_jsonl(&mut jsonl).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            "{\"Id\":{\"String\":\"Str\\\"\"},\"Values\":{\"Array\":[{\"Int\":3},{\"Int\":4}]},\"Tag\":{\"String\":\"a,b\"}}\n"
+        );
+
+        #[cfg(feature = "arrow")]
+        {
+            let batch = exporter.to_record_batch().unwrap();
+            assert_eq!((batch.num_rows(), batch.num_columns()), (1, 3));
+            assert_eq!(batch.schema().field(2).data_type(), &arrow_schema::DataType::UInt64);
+        }
+    }
+}
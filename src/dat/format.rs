@@ -0,0 +1,160 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+
+/// Encoding of the strings in the variable data section.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+pub enum StringEncoding {
+    #[default]
+    Utf16,
+    /// Used by the `.datl` / `.datl64` variants.
+    Utf32,
+}
+
+impl StringEncoding {
+    /// Bytes per code unit, which is also the size of the null terminator.
+    pub fn unit_size(self) -> usize {
+        match self {
+            StringEncoding::Utf16 => 2,
+            StringEncoding::Utf32 => 4,
+        }
+    }
+}
+
+/// Pointer width and string encoding of a dat file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum DatFormat {
+    /// `.dat`: 32-bit pointers, UTF-16 strings.
+    Dat,
+    /// `.dat64` / `.datc64`: 64-bit pointers, UTF-16 strings.
+    Dat64,
+    /// `.datl`: 32-bit pointers, UTF-32 strings.
+    Datl,
+    /// `.datl64`: 64-bit pointers, UTF-32 strings.
+    Datl64,
+}
+
+impl DatFormat {
+    pub fn new(is_64bit: bool, encoding: StringEncoding) -> Self {
+        match (is_64bit, encoding) {
+            (false, StringEncoding::Utf16) => DatFormat::Dat,
+            (true, StringEncoding::Utf16) => DatFormat::Dat64,
+            (false, StringEncoding::Utf32) => DatFormat::Datl,
+            (true, StringEncoding::Utf32) => DatFormat::Datl64,
+        }
+    }
+
+    pub fn is_64bit(self) -> bool {
+        matches!(self, DatFormat::Dat64 | DatFormat::Datl64)
+    }
+
+    pub fn encoding(self) -> StringEncoding {
+        match self {
+            DatFormat::Dat | DatFormat::Dat64 => StringEncoding::Utf16,
+            DatFormat::Datl | DatFormat::Datl64 => StringEncoding::Utf32,
+        }
+    }
+
+    pub fn pointer_size(self) -> usize {
+        if self.is_64bit() { 8 } else { 4 }
+    }
+
+    /// The format implied by the file extension, `None` if it is not a dat extension.
+    pub fn from_filename(filename: &str) -> Option<Self> {
+        let ext = filename.rsplit_once('.')?.1.to_ascii_lowercase();
+        match ext.as_str() {
+            "dat" | "datc" => Some(DatFormat::Dat),
+            "dat64" | "datc64" => Some(DatFormat::Dat64),
+            "datl" | "datlc" => Some(DatFormat::Datl),
+            "datl64" | "datlc64" => Some(DatFormat::Datl64),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a file with no usable name, e.g. one only known by its path hash.
+    ///
+    /// The string encoding is read off the variable data section, the pointer width off the string
+    /// pointers in the rows. Files with no recognisable pointers are assumed 64-bit, like every
+    /// current client file.
+    pub fn detect(data: &[u8]) -> Self {
+        let Some(start) = data.windows(8).position(|w| w == [0xBB; 8]) else {
+            return DatFormat::Dat64;
+        };
+        let encoding = if looks_utf32(&data[start + 8..]) { StringEncoding::Utf32 } else { StringEncoding::Utf16 };
+        let fixed = data.get(4..start).unwrap_or_default();
+        DatFormat::new(looks_64bit(fixed, &data[start..], encoding.unit_size()), encoding)
+    }
+
+    /// `from_filename`, falling back to `detect`.
+    pub fn from_filename_or_data(filename: &str, data: &[u8]) -> Self {
+        Self::from_filename(filename).unwrap_or_else(|| Self::detect(data))
+    }
+}
+
+// UTF-32 text has a zero high half in almost every 4-byte unit; UTF-16 text only does
+// where a string happens to be a single character long.
+fn looks_utf32(var_data: &[u8]) -> bool {
+    let units: Vec<u32> = var_data.chunks_exact(4).map(LittleEndian::read_u32).filter(|&u| u != 0).collect();
+    if units.len() < 4 {
+        return false;
+    }
+    let chars = units.iter().filter(|&&u| u <= 0xFFFF && char::from_u32(u).is_some()).count();
+    chars * 10 >= units.len() * 9
+}
+
+// A 64-bit string pointer is followed by its zero high half. In a 32-bit file the next four bytes
+// are another column, which is rarely zero in every row that holds a string.
+fn looks_64bit(fixed: &[u8], var_data: &[u8], unit: usize) -> bool {
+    // Non-empty, and right after the marker or the previous string's terminator
+    let is_string_start = |offset: usize| offset >= 8
+        && offset + 2 * unit <= var_data.len()
+        && (offset == 8 || var_data[offset - unit..offset].iter().all(|&b| b == 0))
+        && var_data[offset..offset + unit].iter().any(|&b| b != 0);
+    let (mut pointers, mut wide) = (0, 0);
+    for start in 0..fixed.len().saturating_sub(3) {
+        if !is_string_start(LittleEndian::read_u32(&fixed[start..]) as usize) {
+            continue;
+        }
+        pointers += 1;
+        if fixed.get(start + 4..start + 8) == Some(&[0; 4]) {
+            wide += 1;
+        }
+    }
+    pointers == 0 || wide * 10 >= pointers * 9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::reader::DatValue;
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(DatFormat::from_filename("Data/Mods.datc64"), Some(DatFormat::Dat64));
+        assert_eq!(DatFormat::from_filename("Data/French/Mods.DATL64"), Some(DatFormat::Datl64));
+        assert_eq!(DatFormat::from_filename("Mods.dat"), Some(DatFormat::Dat));
+        assert_eq!(DatFormat::from_filename("1a2b3c4d"), None);
+
+        let mut data = 0u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0xBB; 8]);
+        data.extend(['M', 'o', 'd', 's'].iter().flat_map(|&c| (c as u32).to_le_bytes()));
+        assert_eq!(DatFormat::detect(&data), DatFormat::Datl64);
+
+        data.truncate(12);
+        data.extend("Mods and more".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(DatFormat::detect(&data), DatFormat::Dat64);
+
+        // Pointer width from the string pointers of the rows
+        let table = crate::testing::table("Test", vec![
+            crate::testing::column("Id", "string"),
+            crate::testing::column("Level", "i32"),
+            crate::testing::column("Name", "string"),
+        ]);
+        for format in [DatFormat::Dat, DatFormat::Dat64, DatFormat::Datl] {
+            let mut writer = crate::dat::writer::DatWriter::new(&table, format).unwrap();
+            for (i, (id, name)) in [("Str1", "Strength"), ("Dex1", "Dexterity"), ("Int1", "Intelligence")].into_iter().enumerate() {
+                writer.push_row(vec![DatValue::String(id.to_string()), DatValue::Int(i as i64 + 1), DatValue::String(name.to_string())]).unwrap();
+            }
+            assert_eq!(DatFormat::detect(&writer.to_bytes().unwrap()), format);
+        }
+    }
+}
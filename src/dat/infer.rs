@@ -0,0 +1,262 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use super::format::StringEncoding;
+use super::reader::DatReader;
+use super::schema::{Column, Table};
+use super::view::DatStr;
+
+/// A column proposed by [`infer_columns`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InferredColumn {
+    pub offset: usize,
+    pub size: usize,
+    pub r#type: String,
+    pub array: bool,
+}
+
+const MAX_ARRAY_COUNT: u64 = 10_000;
+const MAX_ROW_INDEX: u64 = 1 << 24;
+
+/// Proposes a column layout from the row bytes and variable data section alone.
+///
+/// Columns are claimed left to right, preferring the most specific interpretation that holds for
+/// every row: foreign rows (16/8 bytes with `0xFE` nulls), strings (offsets to valid UTF-16),
+/// arrays (count, offset pairs), self-referencing rows, bools, floats, then plain integers.
+/// Columns that are all zero cannot be told apart and come out as integers.
+pub fn infer_columns(reader: &DatReader<'_>) -> Vec<InferredColumn> {
+    let row_len = match reader.row_length {
+        Some(len) if len > 0 && reader.row_count > 0 => len,
+        _ => return Vec::new(),
+    };
+
+    let data = reader.get_data();
+    let rows: Vec<&[u8]> = (0..reader.row_count as usize)
+        .map(|i| 4 + i * row_len)
+        .take_while(|start| start + row_len <= data.len())
+        .map(|start| &data[start..start + row_len])
+        .collect();
+    let analyzer = Analyzer {
+        rows: &rows,
+        data,
+        var_data_offset: reader.data_section_offset as usize,
+        pointer: reader.format.pointer_size(),
+        encoding: reader.format.encoding(),
+    };
+
+    let mut columns = Vec::new();
+    let mut offset = 0;
+    while offset < row_len {
+        let (r#type, size, array) = analyzer.classify(offset, row_len - offset);
+        columns.push(InferredColumn { offset, size, r#type: r#type.to_string(), array });
+        offset += size;
+    }
+    columns
+}
+
+/// Builds a draft schema table with unnamed columns from [`infer_columns`].
+pub fn infer_table(reader: &DatReader<'_>, name: &str) -> Table {
+    let columns = infer_columns(reader)
+        .into_iter()
+        .map(|c| Column {
+            name: None,
+            description: Some(format!("Inferred at byte offset {}", c.offset)),
+            array: c.array,
+            r#type: c.r#type,
+            unique: false,
+            localized: false,
+            interval: false,
+            references: None,
+        })
+        .collect();
+    Table { name: name.to_string(), columns, tags: None, valid_for: None }
+}
+
+struct Analyzer<'a> {
+    rows: &'a [&'a [u8]],
+    data: &'a [u8],
+    var_data_offset: usize,
+    pointer: usize,
+    encoding: StringEncoding,
+}
+
+impl Analyzer<'_> {
+    fn classify(&self, offset: usize, remaining: usize) -> (&'static str, usize, bool) {
+        let p = self.pointer;
+        if remaining >= 2 * p && self.is_foreign_row(offset) {
+            return ("foreignrow", 2 * p, false);
+        }
+        if remaining >= p && self.is_string(offset) {
+            return ("string", p, false);
+        }
+        if remaining >= 2 * p && self.is_array(offset) {
+            return ("_", 2 * p, true);
+        }
+        if remaining >= p && self.is_row(offset) {
+            return ("row", p, false);
+        }
+        if self.is_bool(offset, remaining) {
+            return ("bool", 1, false);
+        }
+        match remaining {
+            r if r >= 4 && self.is_float(offset) => ("f32", 4, false),
+            r if r >= 4 => ("i32", 4, false),
+            r if r >= 2 => ("i16", 2, false),
+            _ => ("u8", 1, false),
+        }
+    }
+
+    fn pointer_at(&self, row: &[u8], offset: usize) -> u64 {
+        if self.pointer == 8 {
+            LittleEndian::read_u64(&row[offset..offset + 8])
+        } else {
+            LittleEndian::read_u32(&row[offset..offset + 4]) as u64
+        }
+    }
+
+    fn is_null(bytes: &[u8]) -> bool {
+        bytes.iter().all(|&b| b == 0xFE)
+    }
+
+    // Key plus zero padding, or all 0xFE when null. Requires at least one null to tell it from two ints.
+    fn is_foreign_row(&self, offset: usize) -> bool {
+        let p = self.pointer;
+        let mut has_null = false;
+        for row in self.rows {
+            let field = &row[offset..offset + 2 * p];
+            if Self::is_null(field) {
+                has_null = true;
+                continue;
+            }
+            let key = self.pointer_at(row, offset);
+            let padding = self.pointer_at(row, offset + p);
+            if key >= MAX_ROW_INDEX || padding != 0 {
+                return false;
+            }
+        }
+        has_null
+    }
+
+    // Single pointer-sized row index with 0xFE nulls
+    fn is_row(&self, offset: usize) -> bool {
+        let mut has_null = false;
+        for row in self.rows {
+            if Self::is_null(&row[offset..offset + self.pointer]) {
+                has_null = true;
+            } else if self.pointer_at(row, offset) >= MAX_ROW_INDEX {
+                return false;
+            }
+        }
+        has_null
+    }
+
+    fn is_string(&self, offset: usize) -> bool {
+        let mut has_string = false;
+        for row in self.rows {
+            let pointer = self.pointer_at(row, offset);
+            if pointer == 0 { continue; }
+            if !self.is_valid_string(pointer) {
+                return false;
+            }
+            has_string = true;
+        }
+        has_string
+    }
+
+    fn is_valid_string(&self, pointer: u64) -> bool {
+        let unit = self.encoding.unit_size();
+        let start = match (self.var_data_offset as u64).checked_add(pointer) {
+            Some(start) if pointer >= 8 && start.saturating_add(unit as u64) <= self.data.len() as u64 => start,
+            _ => return false,
+        };
+        let s = DatStr::at_with(self.data, start as usize, self.encoding);
+        let end = start as usize + s.as_bytes().len();
+        // Must be terminated inside the file and decode without lone surrogates
+        end + unit <= self.data.len()
+            && self.data[end..end + unit].iter().all(|&b| b == 0)
+            && !s.chars().any(|c| c == char::REPLACEMENT_CHARACTER || (c.is_control() && c != '\n' && c != '\t'))
+    }
+
+    fn is_array(&self, offset: usize) -> bool {
+        let var_len = (self.data.len() - self.var_data_offset) as u64;
+        let mut has_items = false;
+        for row in self.rows {
+            let count = self.pointer_at(row, offset);
+            let pointer = self.pointer_at(row, offset + self.pointer);
+            if count > MAX_ARRAY_COUNT || pointer < 8 || count > var_len.saturating_sub(pointer) {
+                return false;
+            }
+            has_items |= count > 0;
+        }
+        has_items
+    }
+
+    // 0/1 bytes, unless followed by three zero bytes in every row, which reads better as an i32
+    fn is_bool(&self, offset: usize, remaining: usize) -> bool {
+        let all_flags = self.rows.iter().all(|row| row[offset] <= 1);
+        let any_set = self.rows.iter().any(|row| row[offset] == 1);
+        let int_like = remaining >= 4 && self.rows.iter().all(|row| row[offset + 1..offset + 4] == [0, 0, 0]);
+        all_flags && any_set && !int_like
+    }
+
+    fn is_float(&self, offset: usize) -> bool {
+        let mut has_value = false;
+        for row in self.rows {
+            let bits = LittleEndian::read_u32(&row[offset..offset + 4]);
+            if bits == 0 { continue; }
+            let value = f32::from_bits(bits).abs();
+            if !value.is_finite() || !(1e-6..=1e7).contains(&value) {
+                return false;
+            }
+            has_value = true;
+        }
+        has_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_columns() {
+        // Rows of (foreignrow, string, f32, bool, i32) followed by the data section
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for (key, string, float, flag, int) in [(Some(3u64), 8u64, 1.5f32, 1u8, 5i32), (None, 8, 0.25, 0, 7)] {
+            match key {
+                Some(k) => { data.extend_from_slice(&k.to_le_bytes()); data.extend_from_slice(&0u64.to_le_bytes()); },
+                None => data.extend_from_slice(&[0xFE; 16]),
+            }
+            data.extend_from_slice(&string.to_le_bytes());
+            data.extend_from_slice(&float.to_le_bytes());
+            data.push(flag);
+            data.extend_from_slice(&int.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xBB; 8]);
+        for u in "Id".encode_utf16().chain([0]) {
+            data.extend_from_slice(&u.to_le_bytes());
+        }
+
+        let reader = DatReader::new(data, "Test.datc64").unwrap();
+        let types: Vec<String> = infer_columns(&reader).into_iter().map(|c| c.r#type).collect();
+        assert_eq!(types, vec!["foreignrow", "string", "f32", "bool", "i32"]);
+        assert_eq!(infer_table(&reader, "Test").columns.len(), 5);
+    }
+
+    #[test]
+    fn test_infer_huge_pointers() {
+        // (5, -1) reads as an array whose offset overflows, and -1 alone as a string pointer
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for _ in 0..2 {
+            data.extend_from_slice(&5i64.to_le_bytes());
+            data.extend_from_slice(&u64::MAX.to_le_bytes());
+        }
+        data.extend_from_slice(&[0xBB; 8]);
+        data.extend_from_slice(&[0; 8]);
+
+        let reader = DatReader::new(data, "Test.datc64").unwrap();
+        let columns = infer_columns(&reader);
+        assert!(columns.iter().all(|c| !c.array && c.r#type != "string"), "{:?}", columns);
+    }
+}
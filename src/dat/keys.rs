@@ -0,0 +1,156 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::io;
+use serde::Serialize;
+use super::reader::DatReader;
+use super::schema::Table;
+use super::view::{DatValueRef, RowView, TableView};
+
+/// Hashable value of a unique column.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub enum Key {
+    Bool(bool),
+    Int(i64),
+    Long(u64),
+    String(String),
+    Row(usize),
+}
+
+impl Key {
+    /// `None` for nulls, empty strings (how nulls are written in string columns) and floats,
+    /// which are never used as keys.
+    fn from_value(value: DatValueRef<'_>) -> Option<Self> {
+        match value {
+            DatValueRef::Bool(b) => Some(Key::Bool(b)),
+            DatValueRef::Int(i) => Some(Key::Int(i)),
+            DatValueRef::Long(l) => Some(Key::Long(l)),
+            DatValueRef::String(s) if s.is_empty() => None,
+            DatValueRef::String(s) => Some(Key::String(s.to_string())),
+            DatValueRef::ForeignRow(r) | DatValueRef::ForeignKey(r, _) | DatValueRef::EnumRow(r) => Some(Key::Row(r)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Key {
+    fn from(s: &str) -> Self {
+        Key::String(s.to_string())
+    }
+}
+
+impl From<String> for Key {
+    fn from(s: String) -> Self {
+        Key::String(s)
+    }
+}
+
+impl From<i64> for Key {
+    fn from(i: i64) -> Self {
+        Key::Int(i)
+    }
+}
+
+impl From<i32> for Key {
+    fn from(i: i32) -> Self {
+        Key::Int(i as i64)
+    }
+}
+
+impl From<u64> for Key {
+    fn from(l: u64) -> Self {
+        Key::Long(l)
+    }
+}
+
+/// Row lookup by the value of `unique` schema columns. Each column's index is built on its first
+/// lookup; two rows sharing a key make that column's lookups fail, since the schema is then wrong.
+pub struct KeyedTable<'r> {
+    view: TableView<'r>,
+    indexes: Vec<OnceCell<Result<HashMap<Key, u32>, String>>>,
+}
+
+impl<'r> KeyedTable<'r> {
+    pub fn new(reader: &'r DatReader<'r>, table: &'r Table) -> io::Result<Self> {
+        let view = reader.view(table)?;
+        let indexes = table.columns.iter().map(|_| OnceCell::new()).collect();
+        Ok(Self { view, indexes })
+    }
+
+    pub fn view(&self) -> &TableView<'r> {
+        &self.view
+    }
+
+    /// The key -> row index of a unique column, building it if needed.
+    pub fn index(&self, column: &str) -> io::Result<&HashMap<Key, u32>> {
+        let table = self.view.table();
+        let position = self.view.column_index(column)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} not in table {}", column, table.name)))?;
+        if !table.columns[position].unique {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} of {} is not unique", column, table.name)));
+        }
+        self.indexes[position].get_or_init(|| self.build(position))
+            .as_ref()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.clone()))
+    }
+
+    fn build(&self, position: usize) -> Result<HashMap<Key, u32>, String> {
+        let mut index = HashMap::with_capacity(self.view.row_count() as usize);
+        for row in self.view.rows() {
+            let Some(key) = Key::from_value(row.get(position)) else { continue };
+            if let Some(first) = index.insert(key.clone(), row.index()) {
+                let table = self.view.table();
+                return Err(format!("Schema error: {}.{} is marked unique but rows {} and {} both hold {:?}",
+                    table.name, table.columns[position].name.as_deref().unwrap_or("<unnamed>"), first, row.index(), key));
+            }
+        }
+        Ok(index)
+    }
+
+    /// Index of the row whose `column` equals `key`.
+    pub fn find(&self, column: &str, key: impl Into<Key>) -> io::Result<Option<u32>> {
+        Ok(self.index(column)?.get(&key.into()).copied())
+    }
+
+    pub fn get(&self, column: &str, key: impl Into<Key>) -> io::Result<Option<RowView<'_, 'r>>> {
+        Ok(self.find(column, key)?.and_then(|i| self.view.row(i)))
+    }
+}
+
+impl<'a> DatReader<'a> {
+    /// Unique-key lookups over this file, read as `table`.
+    pub fn keyed(&'a self, table: &'a Table) -> io::Result<KeyedTable<'a>> {
+        KeyedTable::new(self, table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::reader::DatValue;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, dat, table};
+
+    #[test]
+    fn test_unique_lookup() {
+        let table = table("Mods", vec![
+            Column { unique: true, ..column("Id", "string") },
+            Column { unique: true, ..column("Hash", "i32") },
+            column("Level", "i32"),
+        ]);
+        let rows = [("Strength1", 7, 1), ("Strength2", 9, 11), ("Dexterity1", 7, 1), ("", 10, 1), ("", 11, 1)].into_iter()
+            .map(|(id, hash, level)| vec![DatValue::String(id.to_string()), DatValue::Int(hash), DatValue::Int(level)])
+            .collect();
+        let reader = dat(&table, rows);
+        let keyed = reader.keyed(&table).unwrap();
+
+        assert_eq!(keyed.find("Id", "Strength2").unwrap(), Some(1));
+        assert_eq!(keyed.get("Id", "Dexterity1").unwrap().unwrap().get(2), DatValueRef::Int(1));
+        assert_eq!(keyed.find("Id", "Missing").unwrap(), None);
+        assert_eq!(keyed.find("Id", "").unwrap(), None);
+
+        let err = keyed.find("Hash", 7).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("rows 0 and 2"));
+        assert!(keyed.find("Level", 1).is_err());
+    }
+}
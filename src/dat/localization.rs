@@ -0,0 +1,201 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use super::reader::{DatReader, DatValue};
+use super::schema::Table;
+
+/// Client languages. English tables live directly in `Data/`, the others in `Data/<Language>/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Language {
+    English,
+    French,
+    German,
+    Japanese,
+    Korean,
+    Portuguese,
+    Russian,
+    SimplifiedChinese,
+    Spanish,
+    Thai,
+    TraditionalChinese,
+}
+
+impl Language {
+    pub const ALL: [Language; 11] = [
+        Language::English, Language::French, Language::German, Language::Japanese, Language::Korean, Language::Portuguese,
+        Language::Russian, Language::SimplifiedChinese, Language::Spanish, Language::Thai, Language::TraditionalChinese,
+    ];
+
+    /// Directory name under `Data/`, `None` for English.
+    pub fn directory(self) -> Option<&'static str> {
+        match self {
+            Language::English => None,
+            Language::French => Some("French"),
+            Language::German => Some("German"),
+            Language::Japanese => Some("Japanese"),
+            Language::Korean => Some("Korean"),
+            Language::Portuguese => Some("Portuguese"),
+            Language::Russian => Some("Russian"),
+            Language::SimplifiedChinese => Some("Simplified Chinese"),
+            Language::Spanish => Some("Spanish"),
+            Language::Thai => Some("Thai"),
+            Language::TraditionalChinese => Some("Traditional Chinese"),
+        }
+    }
+
+    /// `Data/Mods.datc64` -> `Data/French/Mods.datc64`, `Data/Balance/Mods.datc64` ->
+    /// `Data/French/Balance/Mods.datc64`. Paths outside `Data/` get the directory prepended.
+    pub fn localized_path(self, path: &str) -> String {
+        let Some(dir) = self.directory() else { return path.to_string() };
+        match path.split_once('/') {
+            Some((root, rest)) if root.eq_ignore_ascii_case("data") => format!("{}/{}/{}", root, dir, rest),
+            _ => format!("{}/{}", dir, path),
+        }
+    }
+
+    /// Languages with a directory among `paths` (e.g. the bundle index directory list), English always included.
+    pub fn available<'a, I: IntoIterator<Item = &'a str>>(paths: I) -> Vec<Language> {
+        let mut found = vec![Language::English];
+        for path in paths {
+            let mut parts = path.split('/');
+            let (Some(root), Some(dir)) = (parts.next(), parts.next()) else { continue };
+            if !root.eq_ignore_ascii_case("data") { continue; }
+            if let Some(language) = Language::ALL.iter().find(|l| l.directory().is_some_and(|d| d.eq_ignore_ascii_case(dir))) {
+                if !found.contains(language) {
+                    found.push(*language);
+                }
+            }
+        }
+        found.sort();
+        found
+    }
+}
+
+impl FromStr for Language {
+    type Err = io::Error;
+
+    /// Accepts the directory names, case-insensitively, plus `English`.
+    fn from_str(s: &str) -> io::Result<Self> {
+        Language::ALL.iter()
+            .find(|l| s.eq_ignore_ascii_case(l.directory().unwrap_or("English")))
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Unknown language '{}'", s)))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.directory().unwrap_or("English"))
+    }
+}
+
+/// A table in the requested language, backed by the English file.
+///
+/// Columns marked `localized` in the schema are read from the language's file, all others from
+/// English, so rows have the base table's shape whichever file is served.
+pub struct LocalizedTable {
+    pub requested: Language,
+    /// The language actually served: `requested`, or English when its file is missing.
+    pub language: Language,
+    base: DatReader<'static>,
+    localized: Option<DatReader<'static>>,
+}
+
+impl LocalizedTable {
+    /// Loads `path` (the English path, e.g. `Data/Mods.datc64`) through `read`, which returns
+    /// `Ok(None)` for files that do not exist.
+    pub fn load<F>(path: &str, language: Language, mut read: F) -> io::Result<Self>
+    where
+        F: FnMut(&str) -> io::Result<Option<Vec<u8>>>,
+    {
+        let data = read(path)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path)))?;
+        let base = DatReader::new(data, path)?;
+
+        let localized_path = language.localized_path(path);
+        let localized = match language {
+            Language::English => None,
+            _ => match read(&localized_path)? {
+                Some(data) => Some(DatReader::new(data, &localized_path)?),
+                None => None,
+            },
+        };
+        if let Some(localized) = &localized {
+            if localized.row_count != base.row_count || localized.row_length != base.row_length {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "{} has {} rows of {:?} bytes, {} has {} rows of {:?} bytes",
+                    localized_path, localized.row_count, localized.row_length, path, base.row_count, base.row_length)));
+            }
+        }
+
+        let served = if localized.is_some() { language } else { Language::English };
+        Ok(Self { requested: language, language: served, base, localized })
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        self.language != self.requested
+    }
+
+    pub fn base(&self) -> &DatReader<'static> {
+        &self.base
+    }
+
+    /// The served language's file.
+    pub fn reader(&self) -> &DatReader<'static> {
+        self.localized.as_ref().unwrap_or(&self.base)
+    }
+
+    pub fn row_count(&self) -> u32 {
+        self.base.row_count
+    }
+
+    pub fn read_row(&self, index: u32, table: &Table) -> io::Result<Vec<DatValue>> {
+        let mut values = self.base.read_row_expanded(index, table)?;
+        if let Some(localized) = &self.localized {
+            let translated = localized.read_row_expanded(index, table)?;
+            for ((value, translated), col) in values.iter_mut().zip(translated).zip(&table.columns) {
+                if col.localized {
+                    *value = translated;
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, dat_bytes, table};
+
+    fn file(table: &Table, rows: &[(&str, &str)]) -> Vec<u8> {
+        dat_bytes(table, rows.iter().map(|(id, name)| vec![DatValue::String(id.to_string()), DatValue::String(name.to_string())]).collect())
+    }
+
+    #[test]
+    fn test_localized_table() {
+        let table = table("Mods", vec![column("Id", "string"), Column { localized: true, ..column("Name", "string") }]);
+        let mut files = HashMap::new();
+        files.insert("Data/Mods.datc64".to_string(), file(&table, &[("Strength1", "of the Brute")]));
+        files.insert("Data/French/Mods.datc64".to_string(), file(&table, &[("Force1", "de la brute")]));
+        let read = |path: &str| Ok(files.get(path).cloned());
+
+        let french = LocalizedTable::load("Data/Mods.datc64", Language::French, read).unwrap();
+        assert!(!french.is_fallback());
+        assert_eq!(french.read_row(0, &table).unwrap(), vec![DatValue::String("Strength1".to_string()), DatValue::String("de la brute".to_string())]);
+
+        let german = LocalizedTable::load("Data/Mods.datc64", Language::German, read).unwrap();
+        assert!(german.is_fallback());
+        assert_eq!(german.language, Language::English);
+        assert_eq!(german.read_row(0, &table).unwrap()[1], DatValue::String("of the Brute".to_string()));
+
+        assert_eq!(Language::TraditionalChinese.localized_path("Data/Mods.datc64"), "Data/Traditional Chinese/Mods.datc64");
+        assert_eq!(Language::French.localized_path("Data/Balance/Mods.datc64"), "Data/French/Balance/Mods.datc64");
+        assert_eq!(Language::French.localized_path("Mods.datc64"), "French/Mods.datc64");
+        assert_eq!("traditional chinese".parse::<Language>().unwrap(), Language::TraditionalChinese);
+        assert_eq!(Language::available(["data/french", "data/korean/mods.datc64", "art/french"]), vec![Language::English, Language::French, Language::Korean]);
+    }
+}
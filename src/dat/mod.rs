@@ -1,6 +1,21 @@
 pub mod schema;
+pub mod format;
+pub mod boundary;
 pub mod reader;
+pub mod types;
+pub mod view;
+pub mod columns;
+pub mod validate;
+pub mod infer;
+pub mod export;
+pub mod writer;
+pub mod typed;
+pub mod codegen;
 pub mod relational;
+pub mod backrefs;
+pub mod localization;
+pub mod keys;
 pub mod csd;
+pub mod csd_render;
+pub mod csd_match;
 pub mod psg;
-
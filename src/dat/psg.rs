@@ -1,8 +1,14 @@
 use serde::Serialize;
 
+pub const PSG_HEADER_SIZE: usize = 13;
+
 #[derive(Debug, Clone, Serialize)]
 pub struct PsgFile {
-    pub roots: Vec<u32>,
+    pub version: u8,
+    /// The rest of the header, not understood yet.
+    pub header_unknown: [u8; PSG_HEADER_SIZE - 1],
+    /// Connections from the class starting points.
+    pub roots: Vec<PsgConnection>,
     pub groups: Vec<PsgGroup>,
 }
 
@@ -10,15 +16,29 @@ pub struct PsgFile {
 pub struct PsgGroup {
     pub x: f32,
     pub y: f32,
+    pub flags: u32,
+    pub unknown1: u32,
+    pub unknown2: u8,
     pub nodes: Vec<PsgNode>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PsgNode {
+    /// The node's `PassiveSkillGraphId` in `PassiveSkills` (a column value, not a row index).
     pub skill_id: u32,
+    /// Orbit (ring) of the group the node sits on.
     pub radius: u32,
+    /// Slot of the node on its orbit.
     pub position: u32,
-    pub connections: Vec<u32>,
+    pub connections: Vec<PsgConnection>,
+}
+
+/// An edge of the tree. `curvature` is 0 for straight lines; otherwise the edge is an arc, its
+/// sign giving the side it bends to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PsgConnection {
+    pub target: u32,
+    pub curvature: i32,
 }
 
 pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
@@ -56,15 +76,14 @@ pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
         Ok(f32::from_le_bytes(bytes))
     };
 
-    // Header Parsing based on psg2.py
-    // version? u8
-    // Header Parsing based on Gist research: Root starts at 13.
-    // So we skip 13 bytes.
-    let header_size = 13;
-    if data.len() < header_size {
+    // Header: version (u8) then 12 unknown bytes; roots start at 13
+    if data.len() < PSG_HEADER_SIZE {
         return Err("File too small for header".to_string());
     }
-    offset += header_size;
+    let version = read_u8(&mut offset)?;
+    let mut header_unknown = [0u8; PSG_HEADER_SIZE - 1];
+    header_unknown.copy_from_slice(&data[offset..PSG_HEADER_SIZE]);
+    offset = PSG_HEADER_SIZE;
     
     // Root Length (u32)
     let root_length = read_u32(&mut offset)?;
@@ -74,11 +93,10 @@ pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
     
     let mut roots = Vec::new();
     for _ in 0..root_length {
-        let connection = read_u32(&mut offset)?;
-        let _curvature = read_u32(&mut offset)?; // Read 4 bytes for curvature? python says II (two unsigned ints)
-        // Wait, python unpack `<II` is 4 bytes + 4 bytes.
-        
-        roots.push(connection);
+        // Same layout as node connections (psg2.py reads `<II`, but the curvature is signed)
+        let target = read_u32(&mut offset)?;
+        let curvature = read_i32(&mut offset)?;
+        roots.push(PsgConnection { target, curvature });
     }
     
     // Group Length (u32)
@@ -119,9 +137,9 @@ pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
         
         let x = read_f32(&mut offset)?;
         let y = read_f32(&mut offset)?;
-        let _flag = read_u32(&mut offset)?;
-        let _unknown1 = read_u32(&mut offset)?;
-        let _unknown2 = read_u8(&mut offset)?; // This is the 'b'
+        let flags = read_u32(&mut offset)?;
+        let unknown1 = read_u32(&mut offset)?;
+        let unknown2 = read_u8(&mut offset)?; // This is the 'b'
         let passive_length = read_u32(&mut offset)?;
         
         // Skip padding? No padding mentioned.
@@ -139,9 +157,9 @@ pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
             for _ in 0..connections_length {
                 // Connection: connection(I), curvature(i)
                 // Python: `<Ii` = 4 + 4 = 8 bytes.
-                let conn_id = read_u32(&mut offset)?;
-                let _curvature = read_i32(&mut offset)?;
-                connections.push(conn_id);
+                let target = read_u32(&mut offset)?;
+                let curvature = read_i32(&mut offset)?;
+                connections.push(PsgConnection { target, curvature });
             }
             
             nodes.push(PsgNode {
@@ -155,11 +173,16 @@ pub fn parse_psg(data: &[u8]) -> Result<PsgFile, String> {
         groups.push(PsgGroup {
             x,
             y,
+            flags,
+            unknown1,
+            unknown2,
             nodes,
         });
     }
     
     Ok(PsgFile {
+        version,
+        header_unknown,
         roots,
         groups,
     })
@@ -175,15 +198,15 @@ mod tests {
         let mut buffer = Vec::new();
         
         // Header
-        buffer.push(0); // Version
-        // Unknown data (11 bytes)
-        for _ in 0..11 { buffer.push(0); }
+        buffer.push(3); // Version
+        // Unknown data (12 bytes)
+        buffer.extend_from_slice(&[0; 12]);
         
         // Root Length: 1
         buffer.extend_from_slice(&1u32.to_le_bytes());
         // Root 1: id=100, curvature=0
         buffer.extend_from_slice(&100u32.to_le_bytes());
-        buffer.extend_from_slice(&0u32.to_le_bytes());
+        buffer.extend_from_slice(&(-2i32).to_le_bytes());
         
         // Group Length: 1
         buffer.extend_from_slice(&1u32.to_le_bytes());
@@ -191,7 +214,7 @@ mod tests {
         // Group 1 Header
         buffer.extend_from_slice(&500.0f32.to_le_bytes()); // x
         buffer.extend_from_slice(&600.0f32.to_le_bytes()); // y
-        buffer.extend_from_slice(&0u32.to_le_bytes()); // flag
+        buffer.extend_from_slice(&4u32.to_le_bytes()); // flag
         buffer.extend_from_slice(&0u32.to_le_bytes()); // unk1
         buffer.push(0); // unk2 (byte)
         buffer.extend_from_slice(&1u32.to_le_bytes()); // passive_length
@@ -204,14 +227,16 @@ mod tests {
         
         // Node 1 -> Connection 1
         buffer.extend_from_slice(&300u32.to_le_bytes()); // conn_id
-        buffer.extend_from_slice(&0i32.to_le_bytes()); // curvature
+        buffer.extend_from_slice(&7i32.to_le_bytes()); // curvature
         
         let result = parse_psg(&buffer).expect("Failed to parse PSG");
-        assert_eq!(result.roots.len(), 1);
-        assert_eq!(result.roots[0], 100);
+        assert_eq!(result.version, 3);
+        assert_eq!(result.roots, vec![PsgConnection { target: 100, curvature: -2 }]);
         assert_eq!(result.groups.len(), 1);
+        assert_eq!(result.groups[0].flags, 4);
         assert_eq!(result.groups[0].nodes.len(), 1);
         assert_eq!(result.groups[0].nodes[0].skill_id, 200);
-        assert_eq!(result.groups[0].nodes[0].connections[0], 300);
+        assert_eq!((result.groups[0].nodes[0].radius, result.groups[0].nodes[0].position), (10, 5));
+        assert_eq!(result.groups[0].nodes[0].connections[0], PsgConnection { target: 300, curvature: 7 });
     }
 }
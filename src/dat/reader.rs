@@ -1,73 +1,107 @@
 use byteorder::{ByteOrder, LittleEndian};
-use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::borrow::Cow;
+use std::io::{self, Cursor, Read};
+use super::boundary::{boundary_candidates, BoundaryCandidate};
+use super::format::DatFormat;
 use super::schema::{Table, Column};
-use log::debug;
+use super::types::{ColumnSpec, ColumnType};
+use super::view::{DatStr, DatValueRef, RowLayout, TableView};
+use log::{debug, warn};
 
-pub struct DatReader {
-    data: Vec<u8>,
-    pub is_64bit: bool,
+pub struct DatReader<'a> {
+    data: Cow<'a, [u8]>,
+    pub format: DatFormat,
     pub row_count: u32,
     pub row_length: Option<usize>, // If fixed length
     pub data_section_offset: u64,
     pub filename: String,
 }
 
-impl DatReader {
+impl DatReader<'static> {
+    /// The format comes from the extension of `filename`, or from the data if it has none.
+    pub fn new(data: Vec<u8>, filename: &str) -> io::Result<Self> {
+        let format = DatFormat::from_filename_or_data(filename, &data);
+        Self::parse(Cow::Owned(data), filename, format, None)
+    }
+
+    pub fn with_format(data: Vec<u8>, filename: &str, format: DatFormat) -> io::Result<Self> {
+        Self::parse(Cow::Owned(data), filename, format, None)
+    }
+
+    /// Uses the row width of `table` to pick the data section boundary when the file is ambiguous.
+    pub fn with_schema(data: Vec<u8>, filename: &str, table: &Table) -> io::Result<Self> {
+        Self::parse_with_schema(Cow::Owned(data), filename, table)
+    }
+}
+
+impl<'a> DatReader<'a> {
+    /// Reads a table without copying it, e.g. straight from `GgpkReader::get_data_slice` or a bundle buffer.
+    pub fn from_slice(data: &'a [u8], filename: &str) -> io::Result<Self> {
+        Self::parse(Cow::Borrowed(data), filename, DatFormat::from_filename_or_data(filename, data), None)
+    }
+
+    pub fn from_slice_with_format(data: &'a [u8], filename: &str, format: DatFormat) -> io::Result<Self> {
+        Self::parse(Cow::Borrowed(data), filename, format, None)
+    }
+
+    /// `with_schema` without copying the data.
+    pub fn from_slice_with_schema(data: &'a [u8], filename: &str, table: &Table) -> io::Result<Self> {
+        Self::parse_with_schema(Cow::Borrowed(data), filename, table)
+    }
+
+    fn parse_with_schema(data: Cow<'a, [u8]>, filename: &str, table: &Table) -> io::Result<Self> {
+        let format = DatFormat::from_filename_or_data(filename, &data);
+        let width = RowLayout::new(table, format.is_64bit())?.width;
+        Self::parse(data, filename, format, Some(width))
+    }
+
+    /// All data section boundaries considered when parsing, best first, for diagnosing misread tables.
+    pub fn boundary_candidates(&self, width_hint: Option<usize>) -> Vec<BoundaryCandidate> {
+        boundary_candidates(&self.data, self.row_count, self.format, width_hint)
+    }
+
+    pub fn is_64bit(&self) -> bool {
+        self.format.is_64bit()
+    }
+
     pub fn get_data(&self) -> &[u8] {
         &self.data
     }
 
-    pub fn new(data: Vec<u8>, filename: &str) -> io::Result<Self> {
+    /// Allocation-free row and column access over `table`; strings are decoded only when asked for.
+    /// Fails if a column has a type outside the known vocabulary.
+    pub fn view<'r>(&'r self, table: &'r Table) -> io::Result<TableView<'r>> {
+        TableView::new(&self.data, table, self.row_count, self.row_length, self.data_section_offset, self.format)
+    }
 
-        let mut cursor = Cursor::new(data.as_slice());
+    fn parse(data: Cow<'a, [u8]>, filename: &str, format: DatFormat, width_hint: Option<usize>) -> io::Result<Self> {
+
+        let mut cursor = Cursor::new(&data[..]);
         
-        let is_64bit = filename.ends_with(".dat64") || filename.ends_with(".datc64");
+        let is_64bit = format.is_64bit();
 
 
         let row_count = read_u32(&mut cursor)?;
         debug!("DatReader: Loading {}, Row Count: {}, Is 64bit: {}", filename, row_count, is_64bit);
         
-        let mut row_length = None;
+        let row_length;
         let mut data_section_offset = 0;
         
 
         if row_count > 0 {
-             let pattern_32 = [0xBB, 0xBB, 0xBB, 0xBB];
-             let pattern_64 = [0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB, 0xBB];
-             
-
-             
-             let mut found_pattern = false;
-
-             for i in 4..data.len().saturating_sub(4) {
-                 if is_64bit {
-                      if i + 8 <= data.len() && data[i..i+8] == pattern_64 {
-                           let data_size = i - 4;
-                           debug!("DatReader: Found 64-bit pattern at {}, data_size={}, row_count={}", i, data_size, row_count);
-                           if data_size % (row_count as usize) == 0 {
-                               row_length = Some(data_size / (row_count as usize));
-                               data_section_offset = i as u64;
-                               found_pattern = true;
-                               break;
-                           }
-                      }
-                 } else {
-                      if data[i..i+4] == pattern_32 {
-                           let data_size = i - 4;
-                           debug!("DatReader: Found 32-bit pattern at {}, data_size={}, row_count={}", i, data_size, row_count);
-                           if data_size % (row_count as usize) == 0 {
-                               row_length = Some(data_size / (row_count as usize));
-                               data_section_offset = i as u64;
-                               found_pattern = true;
-                               break;
-                           }
-                      }
-                 }
-             }
-
-             if !found_pattern {
+             let candidates = boundary_candidates(&data, row_count, format, width_hint);
+             let Some(best) = candidates.first() else {
                  return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Aligned data boundary not found for row_count {}", row_count)));
+             };
+             if candidates.len() > 1 {
+                 debug!("DatReader: {} boundary candidates for {}, using offset {} (row length {}, {} string hits)",
+                     candidates.len(), filename, best.data_section_offset, best.row_length, best.string_hits);
              }
+             if let Some(width) = width_hint.filter(|_| !best.matches_hint) {
+                 warn!("DatReader: {} rows are {} bytes, schema expects {}", filename, best.row_length, width);
+             }
+             row_length = Some(best.row_length);
+             data_section_offset = best.data_section_offset;
 
         } else {
             debug!("DatReader: Row count is 0 for {}", filename);
@@ -87,7 +121,7 @@ impl DatReader {
         
         Ok(Self {
             data,
-            is_64bit, 
+            format,
             row_count,
             row_length,
             data_section_offset, 
@@ -96,65 +130,27 @@ impl DatReader {
     }
 
     pub fn read_row(&self, index: u32, table: &Table) -> io::Result<Vec<DatValue>> {
+        let view = self.view(table)?;
+        let row = view.row(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Row index out of bounds"))?;
+        Ok(row.values().map(DatValueRef::into_owned).collect())
+    }
 
-        
-        let schema_row_len: usize = table.columns.iter().map(|c| get_column_size(c, self.is_64bit)).sum();
-        let row_len = self.row_length.unwrap_or(schema_row_len);
-
-        let start = 4 + (index as usize * row_len); // 4 bytes for row count
-        if start >= self.data.len() {
-             return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Row index out of bounds"));
-        }
-        
-
-        let end = (start + row_len).min(self.data.len());
-        let mut cursor = Cursor::new(&self.data[start..end]);
-        
-        let mut values = Vec::new();
-        
-        for col in &table.columns {
-
-             let needed = get_column_size(col, self.is_64bit);
-             let current_pos = cursor.position() as usize;
-             if current_pos + needed > cursor.get_ref().len() {
-                 values.push(DatValue::Unknown); // Or Error
-                 continue;
-             }
-             
-
-             match read_column_value(&mut cursor, col, &self.data, self.data_section_offset, self.is_64bit) {
-                 Ok(val) => values.push(val),
-                 Err(_) => values.push(DatValue::Unknown),
-             }
+    /// Like `read_row`, but with every `List` replaced by an `Array` of its values.
+    pub fn read_row_expanded(&self, index: u32, table: &Table) -> io::Result<Vec<DatValue>> {
+        let mut values = self.read_row(index, table)?;
+        for (value, col) in values.iter_mut().zip(&table.columns) {
+            if let DatValue::List(count, offset) = *value {
+                *value = DatValue::Array(self.read_list_values(offset, count, col)?);
+            }
         }
-        
         Ok(values)
     }
 }
 
-fn get_column_size(col: &Column, is_64bit: bool) -> usize {
-    if col.array {
-        return if is_64bit { 16 } else { 8 };
-    }
-    match col.r#type.as_str() {
-        "bool" => 1,
-        "byte" | "u8" => 1,
-        "short" | "u16" => 2,
-        "ushort" => 2,
-        "int" | "i32" | "u32" => 4,
-        "uint" => 4,
-        "float" | "f32" => 4,
-        "long" | "u64" | "i64" => 8,
-        "ulong" => 8,
-        "ref|string" | "string" => if is_64bit { 8 } else { 4 },
-        t if t.starts_with("ref|") || t == "row" => if is_64bit { 8 } else { 4 }, // Generic ref size
-        "foreign_row" | "foreignrow" => if is_64bit { 16 } else { 8 }, // Key(8)+Ptr(8) or Key(4)+Ptr(4)? Usually 16/8 is safe guess for complex foreign keys
-        _ => 4,
-    }
-}
-
-fn read_column_value(cursor: &mut Cursor<&[u8]>, col: &Column, file_data: &[u8], var_data_offset: u64, is_64bit: bool) -> io::Result<DatValue> {
-    if col.array {
+pub(crate) fn read_column_value<'d>(cursor: &mut Cursor<&[u8]>, spec: ColumnSpec, file_data: &'d [u8], var_data_offset: u64, format: DatFormat) -> io::Result<DatValueRef<'d>> {
+    let is_64bit = format.is_64bit();
+    if spec.array {
         let (count, offset) = if is_64bit {
              let c = read_u32(cursor)? as u64;
              let _ = read_u32(cursor)?; // padding
@@ -164,47 +160,59 @@ fn read_column_value(cursor: &mut Cursor<&[u8]>, col: &Column, file_data: &[u8],
         } else {
              (read_u32(cursor)? as u64, read_u32(cursor)? as u64)
         };
-        return Ok(DatValue::List(count as usize, offset));
+        return Ok(DatValueRef::List(count as usize, offset));
     }
 
-    match col.r#type.as_str() {
-        "bool" => {
+    if spec.interval {
+        let min = read_scalar(cursor, spec.ty, file_data, var_data_offset, format)?;
+        let max = read_scalar(cursor, spec.ty, file_data, var_data_offset, format)?;
+        return match (min, max) {
+            (DatValueRef::Int(a), DatValueRef::Int(b)) => Ok(DatValueRef::Interval(a, b)),
+            (DatValueRef::Long(a), DatValueRef::Long(b)) => Ok(DatValueRef::Interval(a as i64, b as i64)),
+            _ => Ok(DatValueRef::Unknown),
+        };
+    }
+
+    read_scalar(cursor, spec.ty, file_data, var_data_offset, format)
+}
+
+fn read_scalar<'d>(cursor: &mut Cursor<&[u8]>, ty: ColumnType, file_data: &'d [u8], var_data_offset: u64, format: DatFormat) -> io::Result<DatValueRef<'d>> {
+    let is_64bit = format.is_64bit();
+    match ty {
+        ColumnType::Bool => {
              let mut b = [0u8; 1];
              cursor.read_exact(&mut b)?;
-             Ok(DatValue::Bool(b[0] != 0))
+             Ok(DatValueRef::Bool(b[0] != 0))
         },
-        "byte" | "u8" => {
+        ColumnType::U8 => {
              let mut b = [0u8; 1];
              cursor.read_exact(&mut b)?;
-             Ok(DatValue::Int(b[0] as i64)) // Treat as int
+             Ok(DatValueRef::Int(b[0] as i64)) // Treat as int
         },
-        "short" | "i16" => {
+        ColumnType::I16 => {
              let mut b = [0u8; 2];
              cursor.read_exact(&mut b)?;
-             Ok(DatValue::Int(LittleEndian::read_i16(&b) as i64))
+             Ok(DatValueRef::Int(LittleEndian::read_i16(&b) as i64))
         },
-        "ushort" | "u16" => {
+        ColumnType::U16 => {
              let mut b = [0u8; 2];
              cursor.read_exact(&mut b)?;
-             Ok(DatValue::Int(LittleEndian::read_u16(&b) as i64))
+             Ok(DatValueRef::Int(LittleEndian::read_u16(&b) as i64))
         },
-        "int" | "i32" => {
-             Ok(DatValue::Int(read_u32(cursor)? as i32 as i64))
+        ColumnType::I32 => {
+             Ok(DatValueRef::Int(read_u32(cursor)? as i32 as i64))
         },
-        "uint" | "u32" => {
-             Ok(DatValue::Int(read_u32(cursor)? as i64))
+        ColumnType::U32 => {
+             Ok(DatValueRef::Int(read_u32(cursor)? as i64))
         },
-        "float" | "f32" => {
+        ColumnType::F32 => {
              let val = read_u32(cursor)?;
-             Ok(DatValue::Float(f32::from_bits(val)))
+             Ok(DatValueRef::Float(f32::from_bits(val)))
         },
-        "long" | "i64" => {
-             Ok(DatValue::Long(read_u64(cursor)?))
+        ColumnType::I64 | ColumnType::U64 => {
+             Ok(DatValueRef::Long(read_u64(cursor)?))
         },
-        "ulong" | "u64" => {
-             Ok(DatValue::Long(read_u64(cursor)?))
-        },
-        "string" | "ref|string" => {
+        ColumnType::String => {
              let offset_val = if is_64bit {
                  let v = read_u32(cursor)? as u64;
                  let _ = read_u32(cursor)?; // padding/flags?
@@ -213,77 +221,40 @@ fn read_column_value(cursor: &mut Cursor<&[u8]>, col: &Column, file_data: &[u8],
                  read_u32(cursor)? as u64
              };
              if offset_val == 0 {
-                 return Ok(DatValue::String("".to_string()));
+                 return Ok(DatValueRef::String(DatStr::default()));
              }
              let abs_offset = var_data_offset + offset_val;
-             if (abs_offset as usize) < file_data.len() {
-                 let s = read_string_at(file_data, abs_offset as usize);
-                 Ok(DatValue::String(s))
-             } else {
-                 Ok(DatValue::String("".to_string()))
+             Ok(DatValueRef::String(DatStr::at_with(file_data, abs_offset as usize, format.encoding())))
+        },
+        ColumnType::ForeignRow | ColumnType::Rid => {
+             let row = read_pointer(cursor, is_64bit)?;
+             let key = read_pointer(cursor, is_64bit)?;
+             if is_null(row, is_64bit) {
+                 return Ok(DatValueRef::Null);
              }
+             Ok(DatValueRef::ForeignKey(row as usize, key))
         },
-        "foreign_row" | "foreignrow" => {
-             let idx = if is_64bit {
-                 let v = read_u32(cursor)? as u64;
-                 let _ = read_u32(cursor)?; // padding
-                 let _ = read_u64(cursor)?; // unknown 2nd part (8 bytes)
-                 v
-             } else {
-                 read_u32(cursor)? as u64
-             };
-             // Existing 32-bit logic was just read_u32.
-             Ok(DatValue::ForeignRow(idx as usize))
+        ColumnType::EnumRow => {
+             let val = read_u32(cursor)?;
+             if val == NULL_32 {
+                 return Ok(DatValueRef::Null);
+             }
+             Ok(DatValueRef::EnumRow(val as usize))
         },
-        t if t.starts_with("ref|") || t == "row" => {
-             // Generic ref
-             let val = if is_64bit {
-                  let v = read_u32(cursor)? as u64;
-                  let _ = read_u32(cursor)?; // padding
-                  v
-             } else {
-                  read_u32(cursor)? as u64
-             };
-             Ok(DatValue::ForeignRow(val as usize)) // Treat as foreign row index
+        ColumnType::Row => {
+             let val = read_pointer(cursor, is_64bit)?;
+             if is_null(val, is_64bit) {
+                 return Ok(DatValueRef::Null);
+             }
+             Ok(DatValueRef::ForeignRow(val as usize))
         },
-        _ => {
-             let size = get_column_size(col, is_64bit);
-             if size > 0 { cursor.seek(SeekFrom::Current(size as i64))?; }
-             Ok(DatValue::Unknown)
-        }
-    }
-}
-
-    // Helper to read string
-fn read_string_at(data: &[u8], offset: usize) -> String {
-    // Try to find null terminator.
-    // Try UTF-16 first (double null aligned)
-    // Safety
-    if offset >= data.len() { return "".to_string(); }
-    
-    // Heuristic: iterate u16s
-    let mut vec_u16 = Vec::new();
-    let mut i = offset;
-    while i + 1 < data.len() {
-        let u = LittleEndian::read_u16(&data[i..i+2]);
-        if u == 0 { break; } // Null terminator
-        vec_u16.push(u);
-        i += 2;
-        if vec_u16.len() > 1000 { break; } // Limit
-    }
-    
-    if !vec_u16.is_empty() {
-        return String::from_utf16_lossy(&vec_u16);
+        ColumnType::Unknown => Ok(DatValueRef::Unknown),
     }
-    
-    // Fallback? empty string
-    "".to_string()
 }
 
-
 use serde::Serialize;
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, PartialEq)]
 pub enum DatValue {
     Bool(bool),
     Int(i64),
@@ -291,10 +262,27 @@ pub enum DatValue {
     Float(f32),
     String(String),
     ForeignRow(usize),
+    ForeignKey(usize, u64), // Row, Key
+    EnumRow(usize),
+    Interval(i64, i64), // Min, Max
     List(usize, u64), // Count, Offset
+    Array(Vec<DatValue>), // List with its values read, see `read_row_expanded`
+    Null,
     Unknown,
 }
 
+// References filled with 0xFE point nowhere
+const NULL_32: u32 = 0xFEFE_FEFE;
+const NULL_64: u64 = 0xFEFE_FEFE_FEFE_FEFE;
+
+fn is_null(val: u64, is_64bit: bool) -> bool {
+    if is_64bit { val == NULL_64 } else { val == NULL_32 as u64 }
+}
+
+fn read_pointer(cursor: &mut Cursor<&[u8]>, is_64bit: bool) -> io::Result<u64> {
+    if is_64bit { read_u64(cursor) } else { Ok(read_u32(cursor)? as u64) }
+}
+
 fn read_u32(cursor: &mut Cursor<&[u8]>) -> io::Result<u32> {
     let mut buf = [0u8; 4];
     cursor.read_exact(&mut buf)?;
@@ -307,43 +295,38 @@ fn read_u64(cursor: &mut Cursor<&[u8]>) -> io::Result<u64> {
     Ok(LittleEndian::read_u64(&buf))
 }
 
-impl DatReader {
+impl DatReader<'_> {
     pub fn read_list_values(&self, offset: u64, count: usize, col: &Column) -> io::Result<Vec<DatValue>> {
         if count == 0 {
              return Ok(Vec::new());
         }
         
-        let start = (self.data_section_offset + offset) as usize;
-        if start >= self.data.len() {
+        let start = self.data_section_offset.saturating_add(offset);
+        if start >= self.data.len() as u64 {
              return Ok(vec![DatValue::Unknown]); 
         }
+        let start = start as usize;
         
         // Element type is same as column type but `array` is false
-        let elem_col = Column {
-            name: None,
-            r#type: col.r#type.clone(),
-            references: col.references.clone(),
-            array: false, 
-            unique: false,
-            localized: false,
-            // until: None, // Removed
-             description: None,
-        };
-        
-        let elem_size = get_column_size(&elem_col, self.is_64bit);
+        let elem_spec = ColumnSpec::from_column(col)?.element();
+        let elem_size = elem_spec.size(self.is_64bit());
         
-        // Safety check for size
+        // The count comes from the file; every element takes at least a byte of the data section
+        let remaining = self.data.len() - start;
+        if count.checked_mul(elem_size.max(1)).is_none_or(|size| size > remaining) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("List of {} items at offset {} overruns the data section", count, offset)));
+        }
+
+        // Unknown element types (`_`) have no size to step by
         if elem_size == 0 { return Ok(vec![DatValue::Unknown; count]); }
 
-        let total_size = elem_size * count;
-        let end = (start + total_size).min(self.data.len());
-        let slice = &self.data[start..end];
+        let slice = &self.data[start..start + elem_size * count];
         let mut cursor = Cursor::new(slice);
         
         let mut values = Vec::new();
         for _ in 0..count {
-             match read_column_value(&mut cursor, &elem_col, &self.data, self.data_section_offset, self.is_64bit) {
-                 Ok(v) => values.push(v),
+             match read_column_value(&mut cursor, elem_spec, &self.data, self.data_section_offset, self.format) {
+                 Ok(v) => values.push(v.into_owned()),
                  Err(_) => values.push(DatValue::Unknown),
              }
         }
@@ -353,3 +336,43 @@ impl DatReader {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{column, table};
+
+    #[test]
+    fn test_null_references() {
+        // One row of (foreignrow, foreignrow, row, enumrow), the first three null
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for row in [[0xFE; 44], [0; 44]] {
+            data.extend_from_slice(&row);
+        }
+        data[4 + 44..4 + 44 + 8].copy_from_slice(&5u64.to_le_bytes());
+        data[4 + 44 + 8..4 + 44 + 16].copy_from_slice(&9u64.to_le_bytes());
+        data.extend_from_slice(&[0xBB; 8]);
+
+        let table = table("Test", vec![column("A", "foreignrow"), column("B", "rid"), column("C", "row"), column("D", "enumrow")]);
+        let reader = DatReader::new(data, "Test.datc64").unwrap();
+        assert_eq!(reader.read_row(0, &table).unwrap(), vec![DatValue::Null; 4]);
+        assert_eq!(reader.read_row(1, &table).unwrap(), vec![
+            DatValue::ForeignKey(5, 9),
+            DatValue::ForeignKey(0, 0),
+            DatValue::ForeignRow(0),
+            DatValue::EnumRow(0),
+        ]);
+    }
+
+    #[test]
+    fn test_list_count_bound() {
+        // The data section holds only the 8 marker bytes
+        let table = table("Test", vec![Column { array: true, ..column("A", "i32") }]);
+        let reader = crate::testing::dat(&table, vec![vec![DatValue::Array(Vec::new())]]);
+        let unknown = Column { array: true, ..column("B", "_") };
+        assert_eq!(reader.read_list_values(0, 0xFEFEFEFE, &table.columns[0]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(reader.read_list_values(0, 0xFEFEFEFE, &unknown).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(reader.read_list_values(0, 2, &table.columns[0]).unwrap(), vec![DatValue::Int(-1_145_324_613); 2]);
+    }
+}
@@ -1 +1,358 @@
-// TODO: Relational logic
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io;
+use serde::Serialize;
+use super::columns::{number, Predicate};
+use super::reader::{DatReader, DatValue};
+use super::schema::Table;
+use super::types::ColumnType;
+use super::view::TableView;
+
+/// Tables loaded side by side so queries can follow references between them.
+#[derive(Default)]
+pub struct Database<'a> {
+    tables: HashMap<String, LoadedTable<'a>>,
+}
+
+struct LoadedTable<'a> {
+    reader: &'a DatReader<'a>,
+    view: TableView<'a>,
+}
+
+impl<'a> Database<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `reader` under `table.name`, replacing any table of the same name.
+    pub fn add(&mut self, reader: &'a DatReader<'a>, table: &'a Table) -> io::Result<()> {
+        let view = reader.view(table)?;
+        self.tables.insert(table.name.clone(), LoadedTable { reader, view });
+        Ok(())
+    }
+
+    pub fn contains(&self, table: &str) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    fn table(&self, name: &str) -> io::Result<&LoadedTable<'a>> {
+        self.tables.get(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Table {} is not loaded", name)))
+    }
+
+    /// The value at `path` for one row, see [`Query`] for the path syntax. Paths through
+    /// array columns give an `Array`, missing or null references `Null`.
+    pub fn resolve(&self, table: &str, row: u32, path: &str) -> io::Result<DatValue> {
+        Ok(self.resolve_path(table, row, path)?.collapse())
+    }
+
+    fn resolve_path(&self, table: &str, row: u32, path: &str) -> io::Result<Resolved> {
+        let segments: Vec<&str> = path.split('.').collect();
+        let mut current: Vec<(String, u32)> = vec![(table.to_string(), row)];
+        let mut resolved = Resolved { values: Vec::new(), many: false };
+
+        for (depth, segment) in segments.iter().enumerate() {
+            let last = depth + 1 == segments.len();
+            let mut next = Vec::new();
+            for (name, row) in &current {
+                let loaded = self.table(name)?;
+                let index = loaded.view.column_index(segment)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} not in table {}", segment, name)))?;
+                let col = &loaded.view.table().columns[index];
+                resolved.many |= loaded.view.layout().specs[index].array;
+
+                let cell = loaded.view.row(*row).map_or(DatValue::Null, |r| r.get(index).into_owned());
+                let values = match cell {
+                    DatValue::List(count, offset) => loaded.reader.read_list_values(offset, count, col)?,
+                    other => vec![other],
+                };
+                if last {
+                    resolved.values.extend(values);
+                    continue;
+                }
+
+                // Follow the reference to the next table
+                let (target, key_column) = match &col.references {
+                    Some(r) => (r.table.as_str(), r.column.as_deref()),
+                    None if loaded.view.layout().specs[index].ty == ColumnType::Row => (name.as_str(), None),
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} of {} is not a reference", segment, name))),
+                };
+                for value in values {
+                    match (value, key_column) {
+                        (DatValue::Null, _) => {},
+                        (DatValue::ForeignRow(r) | DatValue::ForeignKey(r, _), None) => next.push((target.to_string(), r as u32)),
+                        // References by value match rows of the target's key column
+                        (value, Some(key)) => {
+                            resolved.many = true;
+                            next.extend(self.find_rows(target, key, &value)?.into_iter().map(|r| (target.to_string(), r)));
+                        },
+                        (other, None) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Column {} of {} holds {:?}, not a row", segment, name, other))),
+                    }
+                }
+            }
+            current = next;
+        }
+        Ok(resolved)
+    }
+
+    fn find_rows(&self, table: &str, column: &str, value: &DatValue) -> io::Result<Vec<u32>> {
+        let loaded = self.table(table)?;
+        let column = loaded.view.column_by_name(column)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Column {} not in table {}", column, table)))?;
+        Ok(column.filter(&Predicate::Equals(value.clone())))
+    }
+
+    pub fn query(&self, query: &Query) -> io::Result<QueryResult> {
+        let base = self.table(&query.table)?;
+
+        let mut rows = Vec::new();
+        'rows: for row in 0..base.view.row_count() {
+            for condition in &query.conditions {
+                let resolved = self.resolve_path(&query.table, row, &condition.path)?;
+                if resolved.values.iter().any(|v| condition.predicate.matches_value(v)) == condition.negate {
+                    continue 'rows;
+                }
+            }
+            rows.push(row);
+        }
+
+        if !query.order.is_empty() {
+            let mut keyed = Vec::with_capacity(rows.len());
+            for row in rows {
+                let keys = query.order.iter()
+                    .map(|(path, _)| self.resolve(&query.table, row, path))
+                    .collect::<io::Result<Vec<_>>>()?;
+                keyed.push((row, keys));
+            }
+            keyed.sort_by(|(_, a), (_, b)| {
+                query.order.iter().zip(a.iter().zip(b))
+                    .map(|((_, order), (a, b))| match order {
+                        Order::Ascending => compare(a, b),
+                        Order::Descending => compare(b, a),
+                    })
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+            rows = keyed.into_iter().map(|(row, _)| row).collect();
+        }
+        if let Some(limit) = query.limit {
+            rows.truncate(limit);
+        }
+
+        let columns: Vec<String> = if query.select.is_empty() {
+            base.view.table().columns.iter().enumerate()
+                .map(|(i, c)| c.display_name(i))
+                .collect()
+        } else {
+            query.select.clone()
+        };
+        let mut result = QueryResult { table: query.table.clone(), columns, rows: Vec::with_capacity(rows.len()) };
+        for row in rows {
+            let values = if query.select.is_empty() {
+                base.reader.read_row_expanded(row, base.view.table())?
+            } else {
+                query.select.iter().map(|path| self.resolve(&query.table, row, path)).collect::<io::Result<_>>()?
+            };
+            result.rows.push(QueryRow { row, values });
+        }
+        Ok(result)
+    }
+}
+
+struct Resolved {
+    values: Vec<DatValue>,
+    many: bool, // Passed through an array or a by-value reference
+}
+
+impl Resolved {
+    fn collapse(mut self) -> DatValue {
+        match (self.many, self.values.len()) {
+            (true, _) => DatValue::Array(self.values),
+            (false, 0) => DatValue::Null,
+            (false, _) => self.values.swap_remove(0),
+        }
+    }
+}
+
+// A total order: numbers (ints, floats with NaN last, row references), then strings, bools,
+// arrays, other kinds, and nulls last
+fn compare(a: &DatValue, b: &DatValue) -> Ordering {
+    fn rank(v: &DatValue) -> u8 {
+        match v {
+            DatValue::String(_) => 1,
+            DatValue::Bool(_) => 2,
+            DatValue::Array(_) => 3,
+            DatValue::Interval(..) => 4,
+            DatValue::List(..) => 5,
+            DatValue::Unknown => 6,
+            DatValue::Null => 7,
+            _ => 0, // Numbers
+        }
+    }
+    match (a, b) {
+        (DatValue::String(a), DatValue::String(b)) => a.cmp(b),
+        (DatValue::Bool(a), DatValue::Bool(b)) => a.cmp(b),
+        (DatValue::Array(a), DatValue::Array(b)) => a.iter().zip(b).map(|(a, b)| compare(a, b)).find(|o| o.is_ne()).unwrap_or(a.len().cmp(&b.len())),
+        (DatValue::Interval(a0, a1), DatValue::Interval(b0, b1)) => (a0, a1).cmp(&(b0, b1)),
+        (DatValue::List(a0, a1), DatValue::List(b0, b1)) => (a0, a1).cmp(&(b0, b1)),
+        _ => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            _ => rank(a).cmp(&rank(b)),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// Rows of one table, filtered, ordered and projected.
+///
+/// Columns are addressed by paths: `Name` is a column of the queried table, `BaseItemTypesKey.Name`
+/// follows the reference in `BaseItemTypesKey` to the referenced table's `Name`, and so on through
+/// any number of tables. A condition on a path that passes through an array holds if it holds for
+/// any of the values.
+#[derive(Debug, Clone)]
+pub struct Query {
+    table: String,
+    conditions: Vec<Condition>,
+    select: Vec<String>,
+    order: Vec<(String, Order)>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct Condition {
+    path: String,
+    predicate: Predicate,
+    negate: bool,
+}
+
+impl Query {
+    pub fn table(name: &str) -> Self {
+        Self { table: name.to_string(), conditions: Vec::new(), select: Vec::new(), order: Vec::new(), limit: None }
+    }
+
+    pub fn filter(mut self, path: &str, predicate: Predicate) -> Self {
+        self.conditions.push(Condition { path: path.to_string(), predicate, negate: false });
+        self
+    }
+
+    /// Keeps only rows where `predicate` holds for none of the values at `path`.
+    pub fn exclude(mut self, path: &str, predicate: Predicate) -> Self {
+        self.conditions.push(Condition { path: path.to_string(), predicate, negate: true });
+        self
+    }
+
+    /// Output columns; all columns of the table when never called.
+    pub fn select(mut self, paths: &[&str]) -> Self {
+        self.select.extend(paths.iter().map(|p| p.to_string()));
+        self
+    }
+
+    /// Sort key; later calls break ties of earlier ones.
+    pub fn order_by(mut self, path: &str, order: Order) -> Self {
+        self.order.push((path.to_string(), order));
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<QueryRow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryRow {
+    pub row: u32, // Index in the queried table
+    pub values: Vec<DatValue>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, dat, reference};
+
+    fn table(name: &str, columns: Vec<Column>, rows: Vec<Vec<DatValue>>) -> (Table, DatReader<'static>) {
+        let table = crate::testing::table(name, columns);
+        let reader = dat(&table, rows);
+        (table, reader)
+    }
+
+    fn s(v: &str) -> DatValue {
+        DatValue::String(v.to_string())
+    }
+
+    #[test]
+    fn test_query_join() {
+        let (tags, tags_reader) = table("Tags", vec![column("Id", "string")], vec![vec![s("ring")], vec![s("amulet")]]);
+        let (bases, bases_reader) = table(
+            "BaseItemTypes",
+            vec![column("Name", "string"), Column { array: true, references: reference("Tags"), ..column("TagsKeys", "foreignrow") }],
+            vec![
+                vec![s("Iron Ring"), DatValue::Array(vec![DatValue::ForeignKey(0, 0)])],
+                vec![s("Jade Amulet"), DatValue::Array(vec![DatValue::ForeignKey(1, 0)])],
+                vec![s("Gold Ring"), DatValue::Array(vec![DatValue::ForeignKey(0, 0)])],
+            ],
+        );
+        let (uniques, uniques_reader) = table(
+            "Uniques",
+            vec![column("Name", "string"), Column { references: reference("BaseItemTypes"), ..column("BaseItemTypesKey", "foreignrow") }],
+            vec![
+                vec![s("Andvarius"), DatValue::ForeignKey(2, 0)],
+                vec![s("Astramentis"), DatValue::ForeignKey(1, 0)],
+                vec![s("Berek's Grip"), DatValue::ForeignKey(0, 0)],
+                vec![s("Broken"), DatValue::Null],
+            ],
+        );
+
+        let mut db = Database::new();
+        db.add(&tags_reader, &tags).unwrap();
+        db.add(&bases_reader, &bases).unwrap();
+        db.add(&uniques_reader, &uniques).unwrap();
+
+        let query = Query::table("Uniques")
+            .filter("BaseItemTypesKey.TagsKeys.Id", Predicate::Equals(s("ring")))
+            .select(&["Name", "BaseItemTypesKey.Name"])
+            .order_by("Name", Order::Descending);
+        let result = db.query(&query).unwrap();
+        assert_eq!(result.columns, vec!["Name", "BaseItemTypesKey.Name"]);
+        let rows: Vec<(u32, Vec<DatValue>)> = result.rows.into_iter().map(|r| (r.row, r.values)).collect();
+        assert_eq!(rows, vec![(2, vec![s("Berek's Grip"), s("Iron Ring")]), (0, vec![s("Andvarius"), s("Gold Ring")])]);
+
+        assert_eq!(db.resolve("Uniques", 3, "BaseItemTypesKey.Name").unwrap(), DatValue::Null);
+        assert_eq!(db.resolve("Uniques", 1, "BaseItemTypesKey.TagsKeys.Id").unwrap(), DatValue::Array(vec![s("amulet")]));
+        assert!(db.query(&Query::table("Uniques").filter("Missing", Predicate::Range(0.0, 1.0))).is_err());
+        assert_eq!(db.query(&Query::table("Uniques").exclude("Name", Predicate::Contains("A".to_string())).limit(1)).unwrap().rows[0].row, 2);
+    }
+
+    #[test]
+    fn test_order_total() {
+        let values = [f32::NAN, 1.5, -2.0, f32::INFINITY, 0.0];
+        let (floats, reader) = table("Floats", vec![column("Value", "f32")],
+            (0..64).map(|i| vec![DatValue::Float(values[i % values.len()])]).collect());
+        let mut db = Database::new();
+        db.add(&reader, &floats).unwrap();
+        let result = db.query(&Query::table("Floats").order_by("Value", Order::Ascending)).unwrap();
+        let sorted: Vec<f32> = result.rows.iter().map(|r| match r.values[0] { DatValue::Float(f) => f, _ => unreachable!() }).collect();
+        assert_eq!(sorted[0], -2.0);
+        assert_eq!(sorted[sorted.len() - 14], f32::INFINITY);
+        assert!(sorted[sorted.len() - 13..].iter().all(|f| f.is_nan()));
+
+        let mut mixed = [DatValue::Null, s("b"), DatValue::Bool(true), DatValue::Int(3), s("a"), DatValue::Float(f32::NAN), DatValue::ForeignRow(1)];
+        mixed.sort_by(compare);
+        assert_eq!(mixed[..2], [DatValue::ForeignRow(1), DatValue::Int(3)]);
+        assert!(matches!(mixed[2], DatValue::Float(f) if f.is_nan()));
+        assert_eq!(mixed[3..], [s("a"), s("b"), DatValue::Bool(true), DatValue::Null]);
+    }
+}
@@ -27,9 +27,18 @@ pub struct Column {
     pub r#type: String, // "bool", "string", "i32", "f32", "foreign_row", "foreign_row"
     pub unique: bool,
     pub localized: bool,
+    #[serde(default)]
+    pub interval: bool,
     pub references: Option<TableReference>, 
 }
 
+impl Column {
+    /// The column's name, or `Unknown<index>` for unnamed columns.
+    pub fn display_name(&self, index: usize) -> String {
+        self.name.clone().unwrap_or_else(|| format!("Unknown{}", index))
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TableReference {
     pub table: String,
@@ -0,0 +1,61 @@
+// Generated from dat-schema version 7. Do not edit.
+#[allow(unused_imports)]
+use crate::dat::typed::{take, variant, FromDatRow, FromDatValue, RowIndex, RowRef};
+#[allow(unused_imports)]
+use crate::dat::reader::DatValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum Rarity {
+    Normal,
+    Magic,
+}
+
+impl FromDatValue for Rarity {
+    fn from_dat_value(value: DatValue) -> std::io::Result<Self> {
+        variant(&[Rarity::Normal, Rarity::Magic], value, "Rarity")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Mods {
+    pub id: String,
+    pub tags: Vec<Option<RowRef<Tags>>>,
+    pub mod_type_key: Option<RowRef<Mods>>,
+    pub key: Option<RowIndex>,
+    pub stat: Option<RowIndex>,
+    pub rarity: Option<Rarity>,
+    pub level: (i32, i32),
+}
+
+impl FromDatRow for Mods {
+    const TABLE: &'static str = "Mods";
+    const COLUMNS: &'static [&'static str] = &["Id", "Tags", "ModTypeKey", "Key", "Stat", "Rarity", "Level"];
+
+    fn from_row(values: Vec<DatValue>) -> std::io::Result<Self> {
+        let mut values = values.into_iter();
+        Ok(Self {
+            id: take(&mut values, "Id")?,
+            tags: take(&mut values, "Tags")?,
+            mod_type_key: take(&mut values, "ModTypeKey")?,
+            key: take(&mut values, "Key")?,
+            stat: take(&mut values, "Stat")?,
+            rarity: take(&mut values, "Rarity")?,
+            level: take(&mut values, "Level")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Tags {
+}
+
+impl FromDatRow for Tags {
+    const TABLE: &'static str = "Tags";
+    const COLUMNS: &'static [&'static str] = &[];
+
+    fn from_row(values: Vec<DatValue>) -> std::io::Result<Self> {
+        let _ = values;
+        Ok(Self {
+        })
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+use serde::{Serialize, Serializer};
+use super::reader::{DatReader, DatValue};
+use super::schema::Table;
+
+/// A row index into table `T`, as produced by `row` and `foreignrow` columns.
+pub struct RowRef<T> {
+    pub row: usize,
+    _table: PhantomData<fn() -> T>,
+}
+
+impl<T> RowRef<T> {
+    pub fn new(row: usize) -> Self {
+        Self { row, _table: PhantomData }
+    }
+
+    /// Reads the referenced row from `reader`, which must hold table `T`.
+    pub fn get(&self, reader: &DatReader<'_>, table: &Table) -> io::Result<T>
+    where
+        T: FromDatRow,
+    {
+        reader.read_typed(self.row as u32, table)
+    }
+}
+
+// Manual impls: derives would needlessly require `T: Clone` etc.
+impl<T> Clone for RowRef<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RowRef<T> {}
+
+impl<T> PartialEq for RowRef<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.row == other.row
+    }
+}
+
+impl<T> Eq for RowRef<T> {}
+
+impl<T> fmt::Debug for RowRef<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RowRef({})", self.row)
+    }
+}
+
+impl<T> Serialize for RowRef<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.row as u64)
+    }
+}
+
+/// A row index whose table is not known: `rid` columns, and `foreignrow` columns pointing at a
+/// table outside the schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct RowIndex(pub usize);
+
+/// Conversion of one cell into a typed struct field.
+pub trait FromDatValue: Sized {
+    fn from_dat_value(value: DatValue) -> io::Result<Self>;
+}
+
+/// A struct generated from a schema table by [`codegen`](super::codegen).
+pub trait FromDatRow: Sized {
+    const TABLE: &'static str;
+    /// Column names in schema order, empty for unnamed columns.
+    const COLUMNS: &'static [&'static str];
+
+    /// Builds the struct from a row read with `DatReader::read_row_expanded`.
+    fn from_row(values: Vec<DatValue>) -> io::Result<Self>;
+}
+
+impl DatReader<'_> {
+    /// Reads row `index` as `T`. Fails if `table` does not have the columns `T` was generated from.
+    pub fn read_typed<T: FromDatRow>(&self, index: u32, table: &Table) -> io::Result<T> {
+        check_columns::<T>(table)?;
+        T::from_row(self.read_row_expanded(index, table)?)
+    }
+
+    pub fn read_all_typed<T: FromDatRow>(&self, table: &Table) -> io::Result<Vec<T>> {
+        check_columns::<T>(table)?;
+        (0..self.row_count).map(|i| T::from_row(self.read_row_expanded(i, table)?)).collect()
+    }
+}
+
+fn check_columns<T: FromDatRow>(table: &Table) -> io::Result<()> {
+    let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_deref().unwrap_or("")).collect();
+    if names != T::COLUMNS {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "Table {} does not match the columns {} was generated from", table.name, T::TABLE)));
+    }
+    Ok(())
+}
+
+/// Takes the next cell for `column`, used by generated `from_row` implementations.
+pub fn take<T: FromDatValue>(values: &mut impl Iterator<Item = DatValue>, column: &str) -> io::Result<T> {
+    let value = values.next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, format!("Missing column {}", column)))?;
+    T::from_dat_value(value).map_err(|e| io::Error::new(e.kind(), format!("Column {}: {}", column, e)))
+}
+
+/// Maps an enumeration index onto its variant, used by generated enums.
+pub fn variant<T: Copy>(variants: &[T], value: DatValue, name: &str) -> io::Result<T> {
+    let index = match value {
+        DatValue::EnumRow(i) => i,
+        DatValue::Int(i) if i >= 0 => i as usize,
+        other => return Err(unexpected(name, &other)),
+    };
+    variants.get(index).copied()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} has no variant {}", name, index)))
+}
+
+fn unexpected(expected: &str, value: &DatValue) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Expected {}, got {:?}", expected, value))
+}
+
+impl<T: FromDatValue> FromDatValue for Option<T> {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::Null => Ok(None),
+            other => T::from_dat_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: FromDatValue> FromDatValue for Vec<T> {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::Array(items) => items.into_iter().map(T::from_dat_value).collect(),
+            other => Err(unexpected("array", &other)),
+        }
+    }
+}
+
+impl<T> FromDatValue for RowRef<T> {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::ForeignRow(row) | DatValue::ForeignKey(row, _) => Ok(RowRef::new(row)),
+            other => Err(unexpected("row reference", &other)),
+        }
+    }
+}
+
+impl FromDatValue for RowIndex {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::ForeignRow(row) | DatValue::ForeignKey(row, _) => Ok(RowIndex(row)),
+            other => Err(unexpected("row reference", &other)),
+        }
+    }
+}
+
+impl FromDatValue for bool {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::Bool(b) => Ok(b),
+            other => Err(unexpected("bool", &other)),
+        }
+    }
+}
+
+impl FromDatValue for f32 {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::Float(f) => Ok(f),
+            other => Err(unexpected("f32", &other)),
+        }
+    }
+}
+
+impl FromDatValue for String {
+    fn from_dat_value(value: DatValue) -> io::Result<Self> {
+        match value {
+            DatValue::String(s) => Ok(s),
+            other => Err(unexpected("string", &other)),
+        }
+    }
+}
+
+/// Elements of `_` arrays, whose type is unknown.
+impl FromDatValue for () {
+    fn from_dat_value(_: DatValue) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+macro_rules! impl_from_dat_value_int {
+    ($($t:ty),*) => {$(
+        impl FromDatValue for $t {
+            fn from_dat_value(value: DatValue) -> io::Result<Self> {
+                let int = match value {
+                    DatValue::Int(i) => <$t>::try_from(i).ok(),
+                    // Longs hold the raw bits of both i64 and u64 columns
+                    DatValue::Long(l) => <$t>::try_from(l).ok().or_else(|| <$t>::try_from(l as i64).ok()),
+                    DatValue::EnumRow(r) => <$t>::try_from(r).ok(),
+                    other => return Err(unexpected(stringify!($t), &other)),
+                };
+                int.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, concat!("Value out of range for ", stringify!($t))))
+            }
+        }
+
+        impl FromDatValue for ($t, $t) {
+            fn from_dat_value(value: DatValue) -> io::Result<Self> {
+                match value {
+                    DatValue::Interval(min, max) => Ok((<$t>::from_dat_value(DatValue::Int(min))?, <$t>::from_dat_value(DatValue::Int(max))?)),
+                    other => Err(unexpected("interval", &other)),
+                }
+            }
+        }
+    )*};
+}
+
+impl_from_dat_value_int!(u8, i16, u16, i32, u32, i64, u64, usize);
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use serde::Serialize;
+use super::schema::Column;
+
+/// Element type of a schema column, parsed from `Column::type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ColumnType {
+    Bool,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    String,
+    /// Row index into the same table (pointer sized).
+    Row,
+    /// Row index plus key into another table (two pointers).
+    ForeignRow,
+    /// Index into a schema enumeration (4 bytes).
+    EnumRow,
+    /// Row id (two pointers), laid out like `ForeignRow`.
+    Rid,
+    /// `_`: element type of arrays whose contents are not known yet.
+    Unknown,
+}
+
+impl ColumnType {
+    /// Size in bytes of one value in the fixed-width row section.
+    pub fn size(self, is_64bit: bool) -> usize {
+        let pointer = if is_64bit { 8 } else { 4 };
+        match self {
+            ColumnType::Bool | ColumnType::U8 => 1,
+            ColumnType::I16 | ColumnType::U16 => 2,
+            ColumnType::I32 | ColumnType::U32 | ColumnType::F32 | ColumnType::EnumRow => 4,
+            ColumnType::I64 | ColumnType::U64 => 8,
+            ColumnType::String | ColumnType::Row => pointer,
+            ColumnType::ForeignRow | ColumnType::Rid => 2 * pointer,
+            ColumnType::Unknown => 0,
+        }
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(self, ColumnType::U8 | ColumnType::I16 | ColumnType::U16 | ColumnType::I32 | ColumnType::U32 | ColumnType::I64 | ColumnType::U64)
+    }
+
+    /// Name used by the community dat-schema.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ColumnType::Bool => "bool",
+            ColumnType::U8 => "u8",
+            ColumnType::I16 => "i16",
+            ColumnType::U16 => "u16",
+            ColumnType::I32 => "i32",
+            ColumnType::U32 => "u32",
+            ColumnType::I64 => "i64",
+            ColumnType::U64 => "u64",
+            ColumnType::F32 => "f32",
+            ColumnType::String => "string",
+            ColumnType::Row => "row",
+            ColumnType::ForeignRow => "foreignrow",
+            ColumnType::EnumRow => "enumrow",
+            ColumnType::Rid => "rid",
+            ColumnType::Unknown => "_",
+        }
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = io::Error;
+
+    /// Accepts the dat-schema names plus the older PyPoE-style aliases (`int`, `ref|string`, ...).
+    /// PyPoE arrays (`ref|list|int`) are only accepted by `ColumnSpec::from_column`.
+    fn from_str(s: &str) -> io::Result<Self> {
+        Ok(match s {
+            "bool" => ColumnType::Bool,
+            "u8" | "byte" => ColumnType::U8,
+            "i16" | "short" => ColumnType::I16,
+            "u16" | "ushort" => ColumnType::U16,
+            "i32" | "int" => ColumnType::I32,
+            "u32" | "uint" => ColumnType::U32,
+            "i64" | "long" => ColumnType::I64,
+            "u64" | "ulong" => ColumnType::U64,
+            "f32" | "float" => ColumnType::F32,
+            "string" | "ref|string" => ColumnType::String,
+            "row" => ColumnType::Row,
+            "foreignrow" | "foreign_row" => ColumnType::ForeignRow,
+            "enumrow" => ColumnType::EnumRow,
+            "rid" => ColumnType::Rid,
+            // The JSON schema spells an unknown array `array`
+            "_" | "array" => ColumnType::Unknown,
+            other => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unknown column type '{}'", other))),
+        })
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Full shape of a column: element type plus the `array` and `interval` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ColumnSpec {
+    pub ty: ColumnType,
+    pub array: bool,
+    /// Two consecutive values forming a (min, max) range.
+    pub interval: bool,
+}
+
+impl ColumnSpec {
+    pub fn from_column(col: &Column) -> io::Result<Self> {
+        let (ty, list) = match col.r#type.strip_prefix("ref|list|") {
+            Some(element) => (element, true),
+            None => (col.r#type.as_str(), false),
+        };
+        let ty: ColumnType = ty.parse()
+            .map_err(|e: io::Error| io::Error::new(e.kind(), format!("Column {}: {}", col.name.as_deref().unwrap_or("<unnamed>"), e)))?;
+        if col.interval && !ty.is_integer() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Column {}: interval of non-integer type '{}'", col.name.as_deref().unwrap_or("<unnamed>"), ty)));
+        }
+        let array = col.array || list;
+        if ty == ColumnType::Unknown && !array {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Column {}: type '_' is only valid for arrays", col.name.as_deref().unwrap_or("<unnamed>"))));
+        }
+        Ok(Self { ty, array, interval: col.interval })
+    }
+
+    /// The spec of one array element.
+    pub fn element(self) -> Self {
+        Self { array: false, ..self }
+    }
+
+    /// Size in bytes in the fixed-width row section; arrays are a (count, offset) pair.
+    pub fn size(self, is_64bit: bool) -> usize {
+        if self.array {
+            return if is_64bit { 16 } else { 8 };
+        }
+        let size = self.ty.size(is_64bit);
+        if self.interval { 2 * size } else { size }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::column;
+
+    fn spec(column: Column) -> io::Result<ColumnSpec> {
+        ColumnSpec::from_column(&column)
+    }
+
+    #[test]
+    fn test_column_spec() {
+        assert_eq!("ref|string".parse::<ColumnType>().unwrap(), ColumnType::String);
+        assert!("ref|bogus".parse::<ColumnType>().is_err());
+        assert!(spec(column("A", "ref|bogus")).is_err());
+        assert_eq!(spec(column("A", "ref|list|int")).unwrap(), ColumnSpec { ty: ColumnType::I32, array: true, interval: false });
+        assert_eq!(spec(column("A", "ref|list|ref|string")).unwrap().ty, ColumnType::String);
+        assert_eq!(spec(column("A", "foreignrow")).unwrap().size(true), 16);
+        assert_eq!(spec(Column { interval: true, ..column("A", "i32") }).unwrap().size(true), 8);
+        assert_eq!(spec(Column { array: true, ..column("A", "_") }).unwrap().size(false), 8);
+
+        assert!(spec(column("A", "i3")).is_err());
+        assert!(spec(Column { interval: true, ..column("A", "string") }).is_err());
+        assert!(spec(column("A", "_")).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io;
+use super::reader::DatReader;
+use super::schema::Table;
+use super::types::ColumnType;
+use super::view::DatValueRef;
+
+/// Compares a schema table against an actual file, see [`validate`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub table: String,
+    pub filename: String,
+    pub row_count: u32,
+    pub schema_row_length: usize,
+    pub file_row_length: Option<usize>,
+    pub missing_bytes: usize, // Row bytes the schema does not describe
+    pub extra_bytes: usize, // Bytes the schema expects beyond the end of each row
+    pub issues: Vec<ColumnIssue>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnIssue {
+    pub column: usize,
+    pub name: Option<String>,
+    pub kind: IssueKind,
+    pub count: usize, // Number of rows affected
+    pub first_row: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum IssueKind {
+    /// The column lies (partly) past the end of the file's row.
+    Overrun,
+    /// String offset points outside the variable data section.
+    StringOutOfBounds,
+    /// Array (count, offset) pair runs outside the variable data section.
+    ListOutOfBounds,
+    /// Foreign key is at or beyond the row count of the referenced table.
+    ForeignRowOutOfRange { target: String, target_rows: u32 },
+    /// Bool column holding a byte other than 0 or 1.
+    InvalidBool,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing_bytes == 0 && self.extra_bytes == 0 && self.issues.is_empty()
+    }
+
+    fn add_issue(&mut self, column: usize, name: &Option<String>, kind: IssueKind, row: u32, count: usize) {
+        if let Some(issue) = self.issues.iter_mut().find(|i| i.column == column && i.kind == kind) {
+            issue.count += count;
+            return;
+        }
+        self.issues.push(ColumnIssue { column, name: name.clone(), kind, count, first_row: row });
+    }
+}
+
+const DATA_SECTION_MARKER_LEN: u64 = 8;
+
+/// Checks the schema's row width against the file and scans every row for implausible values.
+/// `row_counts` maps table names to their row counts; foreign keys into tables missing from it are not checked.
+/// Fails if the schema uses a column type that is not recognised.
+pub fn validate(reader: &DatReader<'_>, table: &Table, row_counts: &HashMap<String, u32>) -> io::Result<ValidationReport> {
+    let view = reader.view(table)?;
+    let schema_row_length = view.layout().width;
+    let file_row_length = reader.row_length;
+    let row_len = file_row_length.unwrap_or(schema_row_length);
+
+    let mut report = ValidationReport {
+        table: table.name.clone(),
+        filename: reader.filename.clone(),
+        row_count: reader.row_count,
+        schema_row_length,
+        file_row_length,
+        missing_bytes: row_len.saturating_sub(schema_row_length),
+        extra_bytes: schema_row_length.saturating_sub(row_len),
+        issues: Vec::new(),
+    };
+
+    let data_len = reader.get_data().len() as u64;
+    let var_data_offset = reader.data_section_offset;
+    let pointer_size = if reader.is_64bit() { 8 } else { 4 };
+
+    for (index, col) in table.columns.iter().enumerate() {
+        let offset = view.layout().offsets[index];
+        let size = view.layout().sizes[index];
+        if offset + size > row_len {
+            report.add_issue(index, &col.name, IssueKind::Overrun, 0, reader.row_count as usize);
+            continue;
+        }
+
+        let target = match &col.references {
+            Some(r) if r.column.is_none() => Some(r.table.as_str()),
+            None if view.layout().specs[index].ty == ColumnType::Row => Some(table.name.as_str()),
+            _ => None,
+        };
+        let target_rows = target.and_then(|t| row_counts.get(t).copied().or((t == table.name).then_some(reader.row_count)));
+        let elem_size = view.layout().specs[index].element().size(reader.is_64bit()) as u64;
+
+        for row in view.rows() {
+            let bytes = &row.bytes()[offset..offset + size];
+            let kind = match row.get(index) {
+                DatValueRef::List(count, list_offset) => {
+                    let end = (count as u64).checked_mul(elem_size)
+                        .and_then(|size| var_data_offset.checked_add(list_offset)?.checked_add(size));
+                    (count > 0 && (list_offset < DATA_SECTION_MARKER_LEN || end.is_none_or(|end| end > data_len))).then_some(IssueKind::ListOutOfBounds)
+                },
+                DatValueRef::String(_) => {
+                    let pointer = read_pointer(bytes, pointer_size);
+                    let start = var_data_offset.checked_add(pointer);
+                    (pointer != 0 && (pointer < DATA_SECTION_MARKER_LEN || start.is_none_or(|start| start >= data_len))).then_some(IssueKind::StringOutOfBounds)
+                },
+                DatValueRef::ForeignRow(r) | DatValueRef::ForeignKey(r, _) => match (target, target_rows) {
+                    (Some(t), Some(n)) if r >= n as usize => Some(IssueKind::ForeignRowOutOfRange { target: t.to_string(), target_rows: n }),
+                    _ => None,
+                },
+                DatValueRef::Bool(_) if bytes[0] > 1 => Some(IssueKind::InvalidBool),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                report.add_issue(index, &col.name, kind, row.index(), 1);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn read_pointer(bytes: &[u8], pointer_size: usize) -> u64 {
+    if pointer_size == 8 {
+        LittleEndian::read_u64(&bytes[..8])
+    } else {
+        LittleEndian::read_u32(&bytes[..4]) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::reader::DatValue;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, dat, dat_bytes, reference, table};
+
+    fn mods() -> Table {
+        table("Mods", vec![column("Id", "string"), Column { references: reference("Tags"), ..column("Tag", "foreignrow") }])
+    }
+
+    fn rows(tag: usize) -> Vec<Vec<DatValue>> {
+        vec![vec![DatValue::String("Strength1".to_string()), DatValue::ForeignKey(tag, 0)]]
+    }
+
+    fn tag_counts() -> HashMap<String, u32> {
+        [("Tags".to_string(), 2)].into_iter().collect()
+    }
+
+    #[test]
+    fn test_clean_table() {
+        let table = mods();
+        let report = validate(&dat(&table, rows(1)), &table, &tag_counts()).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.file_row_length, Some(report.schema_row_length));
+    }
+
+    #[test]
+    fn test_overrun() {
+        let table = mods();
+        let mut wider = mods();
+        wider.columns.push(column("Level", "i32"));
+        let report = validate(&dat(&table, rows(1)), &wider, &tag_counts()).unwrap();
+        assert_eq!(report.extra_bytes, 4);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!((report.issues[0].column, &report.issues[0].kind), (2, &IssueKind::Overrun));
+    }
+
+    #[test]
+    fn test_string_out_of_bounds() {
+        let table = mods();
+        let mut data = dat_bytes(&table, rows(1));
+        data[4..12].copy_from_slice(&0x7FFF_FFFFu64.to_le_bytes()); // Id pointer of row 0
+        let reader = DatReader::new(data, "Mods.datc64").unwrap();
+        let report = validate(&reader, &table, &tag_counts()).unwrap();
+        assert_eq!(report.issues.iter().map(|i| &i.kind).collect::<Vec<_>>(), vec![&IssueKind::StringOutOfBounds]);
+
+        // A string column drifted over an i64 -1
+        let mut data = dat_bytes(&table, rows(1));
+        data[4..12].copy_from_slice(&u64::MAX.to_le_bytes());
+        let reader = DatReader::new(data, "Mods.datc64").unwrap();
+        let report = validate(&reader, &table, &tag_counts()).unwrap();
+        assert_eq!(report.issues.iter().map(|i| &i.kind).collect::<Vec<_>>(), vec![&IssueKind::StringOutOfBounds]);
+    }
+
+    #[test]
+    fn test_foreign_row_out_of_range() {
+        let table = mods();
+        let report = validate(&dat(&table, rows(2)), &table, &tag_counts()).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::ForeignRowOutOfRange { target: "Tags".to_string(), target_rows: 2 });
+        assert_eq!((report.issues[0].count, report.issues[0].first_row), (1, 0));
+    }
+}
@@ -0,0 +1,311 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::fmt;
+use std::io::{self, Cursor};
+use super::reader::{read_column_value, DatValue};
+use super::schema::Table;
+use super::format::{DatFormat, StringEncoding};
+use super::types::ColumnSpec;
+
+/// A UTF-16LE (or UTF-32LE, for `.datl` files) string in the variable data section, decoded only on demand.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct DatStr<'a> {
+    bytes: &'a [u8], // Without the null terminator
+    encoding: StringEncoding,
+}
+
+impl<'a> DatStr<'a> {
+    /// The null-terminated UTF-16 string starting at `offset`, empty if `offset` is out of bounds.
+    pub fn at(data: &'a [u8], offset: usize) -> Self {
+        Self::at_with(data, offset, StringEncoding::Utf16)
+    }
+
+    pub fn at_with(data: &'a [u8], offset: usize, encoding: StringEncoding) -> Self {
+        if offset >= data.len() { return Self { bytes: &[], encoding }; }
+
+        let unit = encoding.unit_size();
+        let mut end = offset;
+        while end + unit <= data.len() {
+            if data[end..end + unit].iter().all(|&b| b == 0) { break; } // Null terminator
+            end += unit;
+        }
+        Self { bytes: &data[offset..end.min(data.len())], encoding }
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn encoding(&self) -> StringEncoding {
+        self.encoding
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Length in UTF-16 code units.
+    pub fn len_utf16(&self) -> usize {
+        match self.encoding {
+            StringEncoding::Utf16 => self.bytes.len() / 2,
+            StringEncoding::Utf32 => self.chars().map(char::len_utf16).sum(),
+        }
+    }
+
+    /// UTF-16 code units; raw for UTF-16 strings, re-encoded from the decoded chars for UTF-32.
+    pub fn units(&self) -> impl Iterator<Item = u16> + 'a {
+        let (utf16, utf32) = match self.encoding {
+            StringEncoding::Utf16 => (self.bytes, &[][..]),
+            StringEncoding::Utf32 => (&[][..], self.bytes),
+        };
+        let wide = DatStr { bytes: utf32, encoding: StringEncoding::Utf32 };
+        utf16.chunks_exact(2).map(LittleEndian::read_u16)
+            .chain(wide.chars().flat_map(|c| {
+                let mut buf = [0u16; 2];
+                let len = c.encode_utf16(&mut buf).len();
+                buf.into_iter().take(len)
+            }))
+    }
+
+    /// Decodes lazily, replacing invalid code units with U+FFFD.
+    pub fn chars(&self) -> impl Iterator<Item = char> + 'a {
+        let (utf16, utf32) = match self.encoding {
+            StringEncoding::Utf16 => (self.bytes, &[][..]),
+            StringEncoding::Utf32 => (&[][..], self.bytes),
+        };
+        char::decode_utf16(utf16.chunks_exact(2).map(LittleEndian::read_u16))
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .chain(utf32.chunks_exact(4).map(|u| char::from_u32(LittleEndian::read_u32(u)).unwrap_or(char::REPLACEMENT_CHARACTER)))
+    }
+
+    /// Compares against `other` without allocating.
+    pub fn eq_str(&self, other: &str) -> bool {
+        self.units().eq(other.encode_utf16())
+    }
+
+    /// Substring search on the raw code units, without allocating.
+    pub fn contains(&self, needle: &str) -> bool {
+        match self.encoding {
+            StringEncoding::Utf16 => contains_units(self.bytes, 2, needle.encode_utf16().map(u32::from)),
+            StringEncoding::Utf32 => contains_units(self.bytes, 4, needle.chars().map(u32::from)),
+        }
+    }
+}
+
+fn contains_units<I: Iterator<Item = u32> + Clone>(bytes: &[u8], width: usize, needle: I) -> bool {
+    let unit = |i: usize| match width {
+        2 => LittleEndian::read_u16(&bytes[i * 2..]) as u32,
+        _ => LittleEndian::read_u32(&bytes[i * 4..]),
+    };
+    let count = bytes.len() / width;
+    let len = needle.clone().count();
+    len <= count && (0..=count - len).any(|start| needle.clone().enumerate().all(|(j, u)| unit(start + j) == u))
+}
+
+impl fmt::Display for DatStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write;
+        self.chars().try_for_each(|c| f.write_char(c))
+    }
+}
+
+impl fmt::Debug for DatStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+/// Borrowed counterpart of `DatValue`; strings stay undecoded in the file buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatValueRef<'a> {
+    Bool(bool),
+    Int(i64),
+    Long(u64),
+    Float(f32),
+    String(DatStr<'a>),
+    ForeignRow(usize),
+    ForeignKey(usize, u64), // Row, Key
+    EnumRow(usize),
+    Interval(i64, i64),
+    List(usize, u64), // Count, Offset
+    Null,
+    Unknown,
+}
+
+impl DatValueRef<'_> {
+    pub fn into_owned(self) -> DatValue {
+        match self {
+            DatValueRef::Bool(v) => DatValue::Bool(v),
+            DatValueRef::Int(v) => DatValue::Int(v),
+            DatValueRef::Long(v) => DatValue::Long(v),
+            DatValueRef::Float(v) => DatValue::Float(v),
+            DatValueRef::String(s) => DatValue::String(s.to_string()),
+            DatValueRef::ForeignRow(v) => DatValue::ForeignRow(v),
+            DatValueRef::ForeignKey(row, key) => DatValue::ForeignKey(row, key),
+            DatValueRef::EnumRow(v) => DatValue::EnumRow(v),
+            DatValueRef::Interval(min, max) => DatValue::Interval(min, max),
+            DatValueRef::List(count, offset) => DatValue::List(count, offset),
+            DatValueRef::Null => DatValue::Null,
+            DatValueRef::Unknown => DatValue::Unknown,
+        }
+    }
+}
+
+/// Byte offset and size of every column within a row, accumulated from the schema.
+#[derive(Debug, Clone)]
+pub struct RowLayout {
+    pub specs: Vec<ColumnSpec>,
+    pub offsets: Vec<usize>,
+    pub sizes: Vec<usize>,
+    pub width: usize,
+}
+
+impl RowLayout {
+    /// Fails on the first column whose type is not recognised.
+    pub fn new(table: &Table, is_64bit: bool) -> io::Result<Self> {
+        let specs = table.columns.iter().map(ColumnSpec::from_column).collect::<io::Result<Vec<_>>>()?;
+        let sizes: Vec<usize> = specs.iter().map(|s| s.size(is_64bit)).collect();
+        let mut offsets = Vec::with_capacity(sizes.len());
+        let mut width = 0;
+        for size in &sizes {
+            offsets.push(width);
+            width += size;
+        }
+        Ok(Self { specs, offsets, sizes, width })
+    }
+}
+
+/// Rows of a `DatReader` interpreted through a schema table. The layout is computed once;
+/// reading rows and columns afterwards does not allocate.
+pub struct TableView<'r> {
+    data: &'r [u8],
+    table: &'r Table,
+    layout: RowLayout,
+    row_count: u32,
+    row_stride: usize,
+    var_data_offset: u64,
+    format: DatFormat,
+}
+
+impl<'r> TableView<'r> {
+    pub(crate) fn new(data: &'r [u8], table: &'r Table, row_count: u32, row_length: Option<usize>, var_data_offset: u64, format: DatFormat) -> io::Result<Self> {
+        let layout = RowLayout::new(table, format.is_64bit())?;
+        let row_stride = row_length.unwrap_or(layout.width);
+        Ok(Self { data, table, layout, row_count, row_stride, var_data_offset, format })
+    }
+
+    pub fn table(&self) -> &'r Table {
+        self.table
+    }
+
+    pub fn layout(&self) -> &RowLayout {
+        &self.layout
+    }
+
+    pub fn row_count(&self) -> u32 {
+        self.row_count
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.table.columns.iter().position(|c| c.name.as_deref() == Some(name))
+    }
+
+    pub fn row(&self, index: u32) -> Option<RowView<'_, 'r>> {
+        if index >= self.row_count { return None; }
+
+        let start = 4 + index as usize * self.row_stride; // 4 bytes for row count
+        if start >= self.data.len() { return None; }
+        let end = (start + self.row_stride).min(self.data.len());
+        Some(RowView { view: self, index, row: &self.data[start..end] })
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = RowView<'_, 'r>> + '_ {
+        (0..self.row_count).map_while(move |i| self.row(i))
+    }
+
+    /// Reads column `column` of a row slice, `Unknown` if the column overruns the row.
+    pub(crate) fn read_cell(&self, row: &[u8], column: usize) -> DatValueRef<'r> {
+        let (Some(&offset), Some(&size)) = (self.layout.offsets.get(column), self.layout.sizes.get(column)) else {
+            return DatValueRef::Unknown;
+        };
+        if offset + size > row.len() {
+            return DatValueRef::Unknown;
+        }
+        let mut cursor = Cursor::new(&row[offset..offset + size]);
+        read_column_value(&mut cursor, self.layout.specs[column], self.data, self.var_data_offset, self.format)
+            .unwrap_or(DatValueRef::Unknown)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RowView<'v, 'r> {
+    view: &'v TableView<'r>,
+    index: u32,
+    row: &'r [u8],
+}
+
+impl<'r> RowView<'_, 'r> {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Raw fixed-width bytes of this row.
+    pub fn bytes(&self) -> &'r [u8] {
+        self.row
+    }
+
+    pub fn len(&self) -> usize {
+        self.view.table.columns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, column: usize) -> DatValueRef<'r> {
+        self.view.read_cell(self.row, column)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<DatValueRef<'r>> {
+        self.view.column_index(name).map(|i| self.get(i))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = DatValueRef<'r>> + '_ {
+        (0..self.len()).map(move |i| self.get(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::reader::DatReader;
+    use crate::testing::{column, table};
+
+    #[test]
+    fn test_table_view() {
+        // Two rows of (i32, string) followed by the data section
+        let mut data = Vec::new();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&7i32.to_le_bytes());
+        data.extend_from_slice(&8u64.to_le_bytes());
+        data.extend_from_slice(&(-1i32).to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&[0xBB; 8]);
+        for u in "Mods".encode_utf16().chain([0]) {
+            data.extend_from_slice(&u.to_le_bytes());
+        }
+
+        let table = table("Test", vec![column("Key", "i32"), column("Id", "string")]);
+        let reader = DatReader::from_slice(&data, "Test.datc64").unwrap();
+        let view = reader.view(&table).unwrap();
+
+        let row = view.row(0).unwrap();
+        assert_eq!(row.get(0), DatValueRef::Int(7));
+        match row.get_by_name("Id") {
+            Some(DatValueRef::String(s)) => assert!(s.eq_str("Mods")),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(view.row(1).unwrap().get(1), DatValueRef::String(DatStr::default()));
+        assert_eq!(view.rows().count(), 2);
+        assert!(view.row(2).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use super::format::{DatFormat, StringEncoding};
+use super::reader::{DatReader, DatValue};
+use super::schema::Table;
+use super::types::{ColumnSpec, ColumnType};
+use super::view::RowLayout;
+
+const DATA_SECTION_MARKER: [u8; 8] = [0xBB; 8];
+
+/// Serializes rows of `DatValue`s laid out by a schema table back into the dat format.
+///
+/// Arrays must be given as `DatValue::Array` (see `DatReader::read_row_expanded`);
+/// a bare `List` only carries an offset into some other file.
+pub struct DatWriter<'t> {
+    table: &'t Table,
+    layout: RowLayout,
+    format: DatFormat,
+    rows: Vec<Vec<DatValue>>,
+}
+
+impl<'t> DatWriter<'t> {
+    pub fn new(table: &'t Table, format: DatFormat) -> io::Result<Self> {
+        let layout = RowLayout::new(table, format.is_64bit())?;
+        Ok(Self { table, layout, format, rows: Vec::new() })
+    }
+
+    /// Starts from every row of an existing file, with arrays expanded so they can be edited.
+    pub fn from_reader(reader: &DatReader<'_>, table: &'t Table) -> io::Result<Self> {
+        let mut writer = Self::new(table, reader.format)?;
+        for i in 0..reader.row_count {
+            writer.rows.push(reader.read_row_expanded(i, table)?);
+        }
+        Ok(writer)
+    }
+
+    pub fn rows(&self) -> &[Vec<DatValue>] {
+        &self.rows
+    }
+
+    pub fn rows_mut(&mut self) -> &mut Vec<Vec<DatValue>> {
+        &mut self.rows
+    }
+
+    pub fn push_row(&mut self, row: Vec<DatValue>) -> io::Result<()> {
+        if row.len() != self.table.columns.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "Row has {} values, table {} has {} columns", row.len(), self.table.name, self.table.columns.len())));
+        }
+        self.rows.push(row);
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut fixed = Vec::with_capacity(4 + self.rows.len() * self.layout.width);
+        fixed.extend_from_slice(&(self.rows.len() as u32).to_le_bytes());
+
+        let mut var = VarData::new(self.format.encoding());
+        for (index, row) in self.rows.iter().enumerate() {
+            if row.len() != self.layout.specs.len() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("Row {} has {} values, expected {}", index, row.len(), self.layout.specs.len())));
+            }
+            for (column, (value, spec)) in row.iter().zip(&self.layout.specs).enumerate() {
+                self.write_cell(&mut fixed, &mut var, *spec, value).map_err(|e| io::Error::new(e.kind(), format!(
+                    "Row {}, column {}: {}", index, self.table.columns[column].name.as_deref().unwrap_or("<unnamed>"), e)))?;
+            }
+        }
+
+        fixed.extend_from_slice(&var.data);
+        Ok(fixed)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes()?)
+    }
+
+    fn write_cell(&self, out: &mut Vec<u8>, var: &mut VarData, spec: ColumnSpec, value: &DatValue) -> io::Result<()> {
+        if spec.array {
+            let items = match value {
+                DatValue::Array(items) => items.as_slice(),
+                DatValue::Null => &[],
+                other => return Err(mismatch(spec, other)),
+            };
+            // Elements are encoded first so that strings inside them are already interned
+            let mut elements = Vec::with_capacity(items.len() * spec.element().size(self.format.is_64bit()));
+            for item in items {
+                self.write_scalar(&mut elements, var, spec.ty, item)?;
+            }
+            let offset = var.append(&elements);
+            self.write_pointer(out, items.len() as u64)?;
+            return self.write_pointer(out, offset);
+        }
+
+        if spec.interval {
+            let (min, max) = match value {
+                DatValue::Interval(min, max) => (*min, *max),
+                other => return Err(mismatch(spec, other)),
+            };
+            self.write_scalar(out, var, spec.ty, &DatValue::Int(min))?;
+            return self.write_scalar(out, var, spec.ty, &DatValue::Int(max));
+        }
+
+        self.write_scalar(out, var, spec.ty, value)
+    }
+
+    fn write_scalar(&self, out: &mut Vec<u8>, var: &mut VarData, ty: ColumnType, value: &DatValue) -> io::Result<()> {
+        let pointer_size = self.format.pointer_size();
+        let int = match *value {
+            DatValue::Int(i) => Some(i),
+            DatValue::Long(l) => Some(l as i64),
+            _ => None,
+        };
+
+        match (ty, value, int) {
+            (ColumnType::Bool, DatValue::Bool(b), _) => out.push(*b as u8),
+            (ColumnType::U8, _, Some(i)) => out.push(narrow::<u8>(i, ty)?),
+            (ColumnType::I16, _, Some(i)) => out.extend_from_slice(&narrow::<i16>(i, ty)?.to_le_bytes()),
+            (ColumnType::U16, _, Some(i)) => out.extend_from_slice(&narrow::<u16>(i, ty)?.to_le_bytes()),
+            (ColumnType::I32, _, Some(i)) => out.extend_from_slice(&narrow::<i32>(i, ty)?.to_le_bytes()),
+            (ColumnType::U32, _, Some(i)) => out.extend_from_slice(&narrow::<u32>(i, ty)?.to_le_bytes()),
+            (ColumnType::I64 | ColumnType::U64, _, Some(i)) => out.extend_from_slice(&(i as u64).to_le_bytes()),
+            (ColumnType::F32, DatValue::Float(f), _) => out.extend_from_slice(&f.to_le_bytes()),
+            (ColumnType::String, DatValue::String(s), _) => {
+                let offset = var.intern(s);
+                self.write_pointer(out, offset)?;
+            },
+            (ColumnType::Row, DatValue::ForeignRow(r), _) => self.write_pointer(out, *r as u64)?,
+            (ColumnType::ForeignRow | ColumnType::Rid, DatValue::ForeignKey(r, key), _) => {
+                self.write_pointer(out, *r as u64)?;
+                self.write_pointer(out, *key)?;
+            },
+            (ColumnType::ForeignRow | ColumnType::Rid, DatValue::ForeignRow(r), _) => {
+                self.write_pointer(out, *r as u64)?;
+                self.write_pointer(out, 0)?;
+            },
+            (ColumnType::EnumRow, DatValue::EnumRow(r), _) => out.extend_from_slice(&narrow::<u32>(*r as i64, ty)?.to_le_bytes()),
+            // Null references are filled with 0xFE
+            (ColumnType::Row, DatValue::Null, _) => out.extend(std::iter::repeat_n(0xFE, pointer_size)),
+            (ColumnType::ForeignRow | ColumnType::Rid, DatValue::Null, _) => out.extend(std::iter::repeat_n(0xFE, 2 * pointer_size)),
+            (ColumnType::EnumRow, DatValue::Null, _) => out.extend_from_slice(&[0xFE; 4]),
+            // Elements of `_` arrays have no known size, so their bytes cannot be carried through
+            (ColumnType::Unknown, _, _) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Elements of '_' arrays cannot be written")),
+            (_, other, _) => return Err(mismatch(ColumnSpec { ty, array: false, interval: false }, other)),
+        }
+        Ok(())
+    }
+
+    fn write_pointer(&self, out: &mut Vec<u8>, value: u64) -> io::Result<()> {
+        if self.format.is_64bit() {
+            out.extend_from_slice(&value.to_le_bytes());
+        } else {
+            let value = u32::try_from(value)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("Value {} does not fit a 32-bit pointer", value)))?;
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(())
+    }
+}
+
+fn narrow<T: TryFrom<i64>>(value: i64, ty: ColumnType) -> io::Result<T> {
+    T::try_from(value).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("Value {} out of range for {}", value, ty)))
+}
+
+fn mismatch(spec: ColumnSpec, value: &DatValue) -> io::Error {
+    let expected = match (spec.array, spec.interval) {
+        (true, _) => format!("array of {}", spec.ty),
+        (_, true) => format!("interval of {}", spec.ty),
+        _ => spec.ty.to_string(),
+    };
+    io::Error::new(io::ErrorKind::InvalidInput, format!("Expected {}, got {:?}", expected, value))
+}
+
+/// The variable data section; offsets are relative to its start, the marker included.
+struct VarData {
+    data: Vec<u8>,
+    encoding: StringEncoding,
+    strings: HashMap<String, u64>,
+}
+
+impl VarData {
+    fn new(encoding: StringEncoding) -> Self {
+        Self { data: DATA_SECTION_MARKER.to_vec(), encoding, strings: HashMap::new() }
+    }
+
+    fn append(&mut self, bytes: &[u8]) -> u64 {
+        let offset = self.data.len() as u64;
+        self.data.extend_from_slice(bytes);
+        offset
+    }
+
+    /// UTF-16LE (or UTF-32LE) with a 4 byte null terminator, each distinct string written once.
+    fn intern(&mut self, s: &str) -> u64 {
+        if let Some(&offset) = self.strings.get(s) {
+            return offset;
+        }
+        let offset = self.data.len() as u64;
+        match self.encoding {
+            StringEncoding::Utf16 => for unit in s.encode_utf16() {
+                let mut buf = [0u8; 2];
+                LittleEndian::write_u16(&mut buf, unit);
+                self.data.extend_from_slice(&buf);
+            },
+            StringEncoding::Utf32 => for c in s.chars() {
+                self.data.extend_from_slice(&(c as u32).to_le_bytes());
+            },
+        }
+        self.data.extend_from_slice(&[0; 4]);
+        self.strings.insert(s.to_string(), offset);
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dat::schema::Column;
+    use crate::testing::{column, table};
+
+    #[test]
+    fn test_roundtrip() {
+        let table = table("Test", vec![
+            column("Id", "string"),
+            Column { array: true, ..column("Values", "i32") },
+            column("Key", "foreignrow"),
+            Column { interval: true, ..column("Range", "i32") },
+            Column { array: true, ..column("Names", "string") },
+            column("Flag", "bool"),
+        ]);
+        let rows = vec![
+            vec![
+                DatValue::String("Shared".to_string()),
+                DatValue::Array(vec![DatValue::Int(1), DatValue::Int(-2)]),
+                DatValue::ForeignKey(3, 0),
+                DatValue::Interval(5, 10),
+                DatValue::Array(vec![DatValue::String("Shared".to_string()), DatValue::String("Other".to_string())]),
+                DatValue::Bool(true),
+            ],
+            vec![
+                DatValue::String("Shared".to_string()),
+                DatValue::Array(vec![]),
+                DatValue::Null,
+                DatValue::Interval(-1, 1),
+                DatValue::Array(vec![]),
+                DatValue::Bool(false),
+            ],
+        ];
+
+        for (format, filename) in [(DatFormat::Dat64, "Test.datc64"), (DatFormat::Dat, "Test.dat"), (DatFormat::Datl64, "Test.datl64")] {
+            let mut writer = DatWriter::new(&table, format).unwrap();
+            for row in &rows {
+                writer.push_row(row.clone()).unwrap();
+            }
+            let bytes = writer.to_bytes().unwrap();
+
+            let reader = DatReader::new(bytes.clone(), filename).unwrap();
+            assert_eq!(reader.row_count, 2);
+            for (i, row) in rows.iter().enumerate() {
+                assert_eq!(&reader.read_row_expanded(i as u32, &table).unwrap(), row);
+            }
+            // "Shared" is stored once
+            let shared: Vec<u8> = match format.encoding() {
+                StringEncoding::Utf16 => "Shared".encode_utf16().flat_map(u16::to_le_bytes).collect(),
+                StringEncoding::Utf32 => "Shared".chars().flat_map(|c| (c as u32).to_le_bytes()).collect(),
+            };
+            assert_eq!(bytes.windows(shared.len()).filter(|w| *w == shared.as_slice()).count(), 1);
+
+            // Rewriting what was read back gives the same bytes
+            assert_eq!(DatWriter::from_reader(&reader, &table).unwrap().to_bytes().unwrap(), bytes);
+        }
+
+        let mut writer = DatWriter::new(&table, DatFormat::Dat64).unwrap();
+        assert!(writer.push_row(vec![DatValue::Bool(true)]).is_err());
+        writer.push_row(vec![DatValue::Int(1); 6]).unwrap();
+        assert!(writer.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_rejects_lossy_values() {
+        let write = |ty: &str, value: DatValue| {
+            let table = table("Test", vec![column("A", ty)]);
+            let mut writer = DatWriter::new(&table, DatFormat::Dat).unwrap();
+            writer.push_row(vec![value]).unwrap();
+            writer.to_bytes().map_err(|e| e.kind())
+        };
+        assert_eq!(write("u8", DatValue::Int(256)), Err(io::ErrorKind::InvalidInput));
+        assert_eq!(write("i16", DatValue::Int(-32769)), Err(io::ErrorKind::InvalidInput));
+        assert_eq!(write("u32", DatValue::Int(-1)), Err(io::ErrorKind::InvalidInput));
+        assert_eq!(write("row", DatValue::ForeignRow(1 << 32)), Err(io::ErrorKind::InvalidInput));
+        assert!(write("i16", DatValue::Int(-32768)).is_ok());
+
+        let unknown = table("Test", vec![Column { array: true, ..column("A", "_") }]);
+        let mut writer = DatWriter::new(&unknown, DatFormat::Dat64).unwrap();
+        writer.push_row(vec![DatValue::Array(vec![DatValue::Unknown])]).unwrap();
+        assert_eq!(writer.to_bytes().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // Long strings are read back whole
+        let long = "x".repeat(1500);
+        let strings = table("Test", vec![column("A", "string")]);
+        let reader = crate::testing::dat(&strings, vec![vec![DatValue::String(long.clone())]]);
+        assert_eq!(reader.read_row(0, &strings).unwrap(), vec![DatValue::String(long)]);
+    }
+}
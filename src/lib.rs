@@ -21,6 +21,10 @@ pub mod ggpk;
 pub mod bundles;
 pub mod dat;
 pub mod ooz;
+mod versioned;
+
+#[cfg(test)]
+mod testing;
 
 // Re-export commonly used types at crate root
 pub use ggpk::reader::GgpkReader;
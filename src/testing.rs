@@ -0,0 +1,65 @@
+//! Helpers shared by the unit tests.
+
+use std::path::{Path, PathBuf};
+use crate::dat::format::DatFormat;
+use crate::dat::reader::{DatReader, DatValue};
+use crate::dat::schema::{Column, Table, TableReference};
+use crate::dat::writer::DatWriter;
+
+/// A directory under the system temp dir, removed on drop even when the test fails.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("exile_ggpk_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// A named, scalar column of `ty`; set the other fields with struct update syntax.
+pub(crate) fn column(name: &str, ty: &str) -> Column {
+    Column {
+        name: Some(name.to_string()),
+        description: None,
+        array: false,
+        r#type: ty.to_string(),
+        unique: false,
+        localized: false,
+        interval: false,
+        references: None,
+    }
+}
+
+/// A `references` value pointing at the rows of `table`.
+pub(crate) fn reference(table: &str) -> Option<TableReference> {
+    Some(TableReference { table: table.to_string(), column: None })
+}
+
+pub(crate) fn table(name: &str, columns: Vec<Column>) -> Table {
+    Table { name: name.to_string(), columns, tags: None, valid_for: None }
+}
+
+/// `rows` written as a 64-bit dat file of `table`.
+pub(crate) fn dat_bytes(table: &Table, rows: Vec<Vec<DatValue>>) -> Vec<u8> {
+    let mut writer = DatWriter::new(table, DatFormat::Dat64).unwrap();
+    for row in rows {
+        writer.push_row(row).unwrap();
+    }
+    writer.to_bytes().unwrap()
+}
+
+/// `dat_bytes`, opened.
+pub(crate) fn dat(table: &Table, rows: Vec<Vec<DatValue>>) -> DatReader<'static> {
+    DatReader::new(dat_bytes(table, rows), &format!("{}.datc64", table.name)).unwrap()
+}
@@ -0,0 +1,43 @@
+//! The magic and format version that prefix the crate's own cache files, and the bincode
+//! options of what follows.
+
+use std::io::{self, Read, Write};
+use bincode::Options;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Writes `magic` then `version` (u32 LE).
+pub(crate) fn write_header<W: Write>(mut writer: W, magic: [u8; 4], version: u32) -> io::Result<()> {
+    writer.write_all(&magic)?;
+    writer.write_u32::<LittleEndian>(version)
+}
+
+/// Checks the header written by `write_header`; `what` names the file kind in errors.
+pub(crate) fn read_header<R: Read>(mut reader: R, magic: [u8; 4], version: u32, what: &str) -> io::Result<()> {
+    let mut found = [0u8; 4];
+    reader.read_exact(&mut found)?;
+    if found != magic {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Not {} (bad magic)", what)));
+    }
+    let found = reader.read_u32::<LittleEndian>()?;
+    if found != version {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Format version {} of {}, expected {}", found, what, version)));
+    }
+    Ok(())
+}
+
+/// The bincode encoding of the body after the header; the same layout as `bincode::serialize`.
+pub(crate) fn serialize_into<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> io::Result<()> {
+    options().serialize_into(writer, value).map_err(io::Error::other)
+}
+
+/// Decodes a body written by `serialize_into`. Length prefixes are bounded by `limit`, normally the
+/// file length, so a corrupt prefix is an error instead of a huge allocation.
+pub(crate) fn deserialize_from<R: Read, T: DeserializeOwned>(reader: R, limit: u64) -> bincode::Result<T> {
+    options().with_limit(limit).deserialize_from(reader)
+}
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().with_fixint_encoding().allow_trailing_bytes()
+}